@@ -0,0 +1,38 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use mandelbrot::color::{write_colored_data, write_colored_lut_data, write_data, write_lut_data, Gradient, GrayLut, PaletteLut};
+use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+use mandelbrot::geometry::{Range, Rect, Resolution};
+use mandelbrot::grid::EscapeGrid;
+use mandelbrot::image::{Image, PixelFormat};
+
+const RESOLUTION: Resolution = Resolution{width: 2048, height: 2048};
+const LIMIT: u16 = 1000;
+const REGION: Rect<f32> = Rect{x: Range{min: -2.0, max: 1.0}, y: Range{min: -1.5, max: 1.5}};
+
+/// Coloring only: the escape counts are computed once up front.
+fn bench_coloring(c: &mut Criterion){
+    let calculations = make_calculations(RESOLUTION, REGION, LIMIT, Precision::Single, EscapeParams::default()).unwrap();
+    let grid = EscapeGrid::collect(&RESOLUTION, calculations).unwrap();
+    let gradient = Gradient::ultra_fractal();
+
+    let mut group = c.benchmark_group("color_2048");
+    group.sample_size(20);
+    let mut gray = Image::new(&RESOLUTION);
+    group.bench_function("gray_direct", |b| b.iter(|| write_data(&mut gray, black_box(&grid).iter(), LIMIT, 2.2).unwrap()));
+    group.bench_function("gray_lut", |b| b.iter(|| {
+        let lut = GrayLut::new(LIMIT, 2.2);
+        write_lut_data(&mut gray, black_box(&grid).iter(), &lut).unwrap()
+    }));
+    let mut colored = Image::with_format(&RESOLUTION, PixelFormat::Rgb8);
+    group.bench_function("palette_direct", |b| b.iter(|| write_colored_data(&mut colored, black_box(&grid).iter(), LIMIT, &gradient).unwrap()));
+    group.bench_function("palette_lut", |b| b.iter(|| {
+        let lut = PaletteLut::new(LIMIT, &gradient);
+        write_colored_lut_data(&mut colored, black_box(&grid).iter(), &lut).unwrap()
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, bench_coloring);
+criterion_main!(benches);
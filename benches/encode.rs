@@ -0,0 +1,61 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use mandelbrot::compute::{EscapeParams, Precision};
+use mandelbrot::geometry::{Range, Rect, Resolution};
+use mandelbrot::image::{encode_image, encode_png, Image, OutputFormat};
+use mandelbrot::render::render_parallel;
+
+const RESOLUTION: Resolution = Resolution{width: 4096, height: 4096};
+/// Size of a stitched atlas at the default resolution, the largest image `save_image` writes.
+const STITCHED_RESOLUTION: Resolution = Resolution{width: 8192, height: 8192};
+const LIMIT: u16 = 256;
+const REGION: Rect<f32> = Rect{x: Range{min: -2.0, max: 1.0}, y: Range{min: -1.5, max: 1.5}};
+
+fn render(resolution: Resolution) -> Image{
+    let mut image = Image::new(&resolution);
+    render_parallel(&mut image, black_box(REGION), LIMIT, Precision::Single, EscapeParams::default(), 1.0).unwrap();
+    image
+}
+
+/// The `png` crate at its default compression, which is comparable to `encode_png`'s but runs on one
+/// thread. The difference grows with the number of cores.
+fn encode_single_threaded(image: &Image) -> Vec<u8>{
+    let mut output = Vec::new();
+    let mut encoder = png::Encoder::new(&mut output, image.resolution.width, image.resolution.height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&image.data).unwrap();
+    writer.finish().unwrap();
+    output
+}
+
+fn bench_encode(c: &mut Criterion){
+    let image = render(RESOLUTION);
+    let mut group = c.benchmark_group("encode_png_4096");
+    group.sample_size(10);
+    group.bench_function("single_threaded", |b| b.iter(|| encode_single_threaded(black_box(&image))));
+    group.bench_function("parallel", |b| b.iter(|| encode_png(black_box(&image), None).unwrap()));
+    group.finish();
+}
+
+fn bench_render_and_encode(c: &mut Criterion){
+    let mut group = c.benchmark_group("render_and_encode_4096");
+    group.sample_size(10);
+    group.bench_function("single_threaded", |b| b.iter(|| encode_single_threaded(&render(RESOLUTION))));
+    group.bench_function("parallel", |b| b.iter(|| encode_png(&render(RESOLUTION), None).unwrap()));
+    group.finish();
+}
+
+fn bench_encode_stitched(c: &mut Criterion){
+    let image = render(STITCHED_RESOLUTION);
+    let mut group = c.benchmark_group("encode_png_8192");
+    group.sample_size(10);
+    group.bench_function("single_threaded", |b| b.iter(|| encode_single_threaded(black_box(&image))));
+    group.bench_function("encode_image", |b| b.iter(|| encode_image(black_box(&image), OutputFormat::Png).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode, bench_render_and_encode, bench_encode_stitched);
+criterion_main!(benches);
@@ -0,0 +1,60 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use mandelbrot::compute::{calculate_escape_time, make_calculations, EscapeLimit, EscapeParams, Periodicity, Precision};
+use mandelbrot::geometry::{Range, Rect, Resolution};
+use num::Complex;
+
+const LIMIT: EscapeLimit = 256;
+
+/// Most points inside the main cardioid and the period-2 bulb.
+const INTERIOR: Rect<f32> = Rect{x: Range{min: -1.0, max: 0.2}, y: Range{min: -0.4, max: 0.4}};
+/// Seahorse valley, nearly every point close to the boundary.
+const BOUNDARY: Rect<f32> = Rect{x: Range{min: -0.76, max: -0.73}, y: Range{min: 0.08, max: 0.11}};
+/// Everything escapes within a few iterations.
+const EXTERIOR: Rect<f32> = Rect{x: Range{min: 1.0, max: 2.0}, y: Range{min: 1.0, max: 2.0}};
+
+fn grid(rect: Rect<f32>, side: u32) -> Vec<Complex<f32>>{
+    let step = |range: Range<f32>, i: u32| range.min + (range.max - range.min) * i as f32 / side as f32;
+    (0..side)
+        .flat_map(|y| (0..side).map(move |x| (x, y)))
+        .map(|(x, y)| Complex::<f32>{re: step(rect.x, x), im: step(rect.y, y)})
+        .collect()
+}
+
+fn bench_escape_time(c: &mut Criterion){
+    let mut group = c.benchmark_group("calculate_escape_time");
+    let params = EscapeParams::default();
+    let regions = [
+        ("interior", INTERIOR, params),
+        ("interior_no_cardioid_check", INTERIOR, EscapeParams{cardioid_check: false, ..params}),
+        // Against `interior_no_cardioid_check`, what periodicity checking saves on interior points.
+        ("interior_periodicity", INTERIOR, EscapeParams{cardioid_check: false, periodicity: Some(Periodicity::default()), ..params}),
+        ("boundary", BOUNDARY, params),
+        ("exterior", EXTERIOR, params),
+    ];
+    for (name, rect, params) in regions {
+        let points = grid(rect, 32);
+        group.bench_function(name, |b| b.iter(|| {
+            for &point in &points {
+                black_box(calculate_escape_time(black_box(point), LIMIT, params));
+            }
+        }));
+    }
+    group.finish();
+}
+
+fn bench_make_calculations(c: &mut Criterion){
+    let mut group = c.benchmark_group("make_calculations");
+    let resolution = Resolution{width: 64, height: 64};
+    for (name, rect) in [("interior", INTERIOR), ("boundary", BOUNDARY), ("exterior", EXTERIOR)] {
+        group.bench_function(name, |b| b.iter(|| {
+            make_calculations(resolution, black_box(rect), LIMIT, Precision::Single, EscapeParams::default()).unwrap()
+                .for_each(|pixel| { black_box(pixel); });
+        }));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_escape_time, bench_make_calculations);
+criterion_main!(benches);
@@ -0,0 +1,98 @@
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use num::Complex;
+
+use crate::compute::{EscapeLimit, EscapeParams, Precision};
+use crate::geometry::{Rect, RectError, Resolution};
+use crate::image::{save_image_with_metadata, Image, SaveError};
+use crate::metadata::RenderMetadata;
+use crate::render::render_parallel;
+
+/// Zoom of `rect` in `Rect::from_center_zoom` terms.
+fn zoom_of(rect: &Rect<f32>, resolution: &Resolution) -> f32{
+    let short_extent = if resolution.width >= resolution.height { rect.y.span() } else { rect.x.span() };
+    Rect::FULL_VIEW_EXTENT / short_extent
+}
+
+/// Views for a zoom from `start` into `target`, ending at `final_zoom`. The zoom grows geometrically,
+/// so every frame magnifies by the same factor, and the target keeps its place on screen while the
+/// view drifts onto it.
+pub fn zoom_sequence(start: Rect<f32>, target: Complex<f32>, final_zoom: f32, frames: u32, resolution: &Resolution) -> Vec<Rect<f32>>{
+    let start_zoom = zoom_of(&start, resolution);
+    let start_center = Complex::<f32>{re: (start.x.min + start.x.max) / 2.0, im: (start.y.min + start.y.max) / 2.0};
+    let last = frames.saturating_sub(1).max(1) as f32;
+
+    (0..frames)
+        .map(|frame| {
+            let t = frame as f32 / last;
+            let zoom = start_zoom * (final_zoom / start_zoom).powf(t);
+            let center = target + (start_center - target) * (start_zoom / zoom) * (1.0 - t);
+            Rect::from_center_zoom(center, zoom, resolution)
+        })
+        .collect()
+}
+
+/// Name of the `index`th frame, zero padded so the frames sort in order (at least four digits, as
+/// `ffmpeg -i frame_%04d.png` expects).
+pub fn frame_file_name(index: u32, frames: u32) -> String{
+    let width = frames.saturating_sub(1).to_string().len().max(4);
+    format!("frame_{index:0width$}.png")
+}
+
+#[derive(Debug)]
+pub enum AnimationError{
+    /// The view of frame `frame` cannot be rendered, e.g. because the zoom ran past what f32 holds.
+    Rect{frame: u32, source: RectError},
+    Save(SaveError),
+}
+
+impl fmt::Display for AnimationError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            AnimationError::Rect{frame, source} => write!(f, "frame {frame}: {source}"),
+            AnimationError::Save(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for AnimationError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            AnimationError::Rect{source, ..} => Some(source),
+            AnimationError::Save(err) => Some(err),
+        }
+    }
+}
+
+impl From<SaveError> for AnimationError{
+    fn from(err: SaveError) -> Self{
+        AnimationError::Save(err)
+    }
+}
+
+/// Renders `views` into numbered PNGs inside `dir`, calling `on_frame` after each one.
+pub fn render_frames(
+        views: &[Rect<f32>],
+        resolution: Resolution,
+        limit: EscapeLimit,
+        params: EscapeParams,
+        gamma: f32,
+        dir: impl AsRef<Path>,
+        mut on_frame: impl FnMut(u32),
+    ) -> Result<(), AnimationError> {
+    let frames = views.len() as u32;
+    for (index, rect) in views.iter().enumerate() {
+        let index = index as u32;
+        let mut image = Image::new(&resolution);
+        let precision = Precision::for_rect(rect, &resolution);
+        render_parallel(&mut image, *rect, limit, precision, params, gamma)
+            .map_err(|source| AnimationError::Rect{frame: index, source})?;
+
+        let metadata = RenderMetadata{rect: *rect, resolution, limit, kind: params.kind, overlap: 0};
+        save_image_with_metadata(&image, dir.as_ref().join(frame_file_name(index, frames)), &metadata)?;
+        on_frame(index);
+    }
+    Ok(())
+}
@@ -0,0 +1,1133 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ::image::ImageError;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::color::{write_data, write_normalized_data};
+use crate::compute::{coordinate_mapper, escape_time_at, make_calculations_by_row, make_calculations_with_overlap, AdaptiveLimit, EscapeLimit, EscapeParams, Precision};
+use crate::geometry::{make_lerp, Range, Rect, Resolution};
+use crate::grid::EscapeGrid;
+use crate::image::{save_image_with_metadata, tile_is_interesting, tile_is_mixed, Image, PixelFormat, SaveError, DEFAULT_MIN_CONTRAST};
+use crate::metadata::{read_metadata, MetadataError, RenderMetadata};
+
+/// File name of the atlas tile in column `x` and row `y` (counted from the bottom) of a grid with
+/// `tiles` tiles along its longer side. Indices are zero padded to the same width for the whole grid, at least three
+/// digits, so names sort by position; the exact rect is kept in the tile's metadata.
+///
+/// ```
+/// use std::collections::HashSet;
+/// use mandelbrot::atlas::tile_file_name;
+///
+/// assert_eq!(tile_file_name(7, 42, 100), "tile_x007_y042.png");
+/// assert_eq!(tile_file_name(7, 42, 2000), "tile_x0007_y0042.png");
+/// for tiles in [1, 2, 10, 1000, 1001] {
+///     let names: Vec<_> = (0..tiles.min(40)).flat_map(|x| (0..tiles.min(40)).map(move |y| tile_file_name(x, y, tiles))).collect();
+///     assert_eq!(names.iter().collect::<HashSet<_>>().len(), names.len());
+///     let mut sorted = names.clone();
+///     sorted.sort();
+///     assert_eq!(sorted, names);
+/// }
+/// // Neighbours across the last column and row of a large grid stay distinct.
+/// assert_ne!(tile_file_name(998, 999, 1000), tile_file_name(999, 999, 1000));
+/// assert_ne!(tile_file_name(999, 998, 1000), tile_file_name(999, 999, 1000));
+/// ```
+pub fn tile_file_name(x: u32, y: u32, tiles: u32) -> String{
+    let width = tiles.saturating_sub(1).to_string().len().max(3);
+    format!("tile_x{x:0width$}_y{y:0width$}.png")
+}
+
+/// Whether a previous run already finished writing the tile at `path`. Tiles are only moved into
+/// place once fully written, so any non-empty file there is complete. `run_atlas` leaves such tiles
+/// alone unless `force` is set.
+pub fn tile_is_done(path: impl AsRef<Path>) -> bool{
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.len() > 0)
+}
+
+fn partial_path(path: &Path) -> PathBuf{
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    PathBuf::from(partial)
+}
+
+/// Saves the tile next to `path` and renames it into place, so an interrupted run never leaves a
+/// truncated tile behind under its final name.
+pub fn save_tile(img: &Image, path: impl AsRef<Path>, metadata: &RenderMetadata) -> Result<(), SaveError>{
+    let path = path.as_ref();
+    let partial = partial_path(path);
+    save_image_with_metadata(img, &partial, metadata)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Name the command line gives the checkpoint inside the atlas directory.
+pub const CHECKPOINT_FILE_NAME: &str = "checkpoint.json";
+
+/// Completed tiles between checkpoint saves during a run. The checkpoint is saved once more at the end.
+const CHECKPOINT_INTERVAL: usize = 16;
+
+/// Tiles of an atlas run that need no more work, either written or skipped as uniform, along with
+/// the settings they were rendered with.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Checkpoint{
+    /// Columns and rows of the grid the positions refer to.
+    pub cols:       u32,
+    pub rows:       u32,
+    pub region:     Rect<f32>,
+    pub resolution: Resolution,
+    pub limit:      EscapeLimit,
+    pub overlap:    u32,
+    /// `(x, y)` positions as in `tile_file_name`.
+    pub completed:  BTreeSet<(u32, u32)>,
+}
+
+#[derive(Debug)]
+pub enum CheckpointError{
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for CheckpointError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            CheckpointError::Io(err) => write!(f, "could not access checkpoint: {err}"),
+            CheckpointError::Json(err) => write!(f, "invalid checkpoint: {err}"),
+        }
+    }
+}
+
+impl Error for CheckpointError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            CheckpointError::Io(err) => Some(err),
+            CheckpointError::Json(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError{
+    fn from(err: io::Error) -> Self{
+        CheckpointError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for CheckpointError{
+    fn from(err: serde_json::Error) -> Self{
+        CheckpointError::Json(err)
+    }
+}
+
+impl Checkpoint{
+    /// An empty checkpoint for a run of `config`.
+    pub fn new(config: &AtlasConfig) -> Self{
+        Checkpoint{
+            cols:       config.cols,
+            rows:       config.rows,
+            region:     config.region,
+            resolution: config.resolution,
+            limit:      config.limit,
+            overlap:    config.overlap,
+            completed:  BTreeSet::new(),
+        }
+    }
+
+    /// Whether the tiles recorded here were rendered the way `config` would render them. A checkpoint
+    /// of a different grid, region, resolution, limit or overlap says nothing about this run.
+    pub fn matches(&self, config: &AtlasConfig) -> bool{
+        Checkpoint{completed: BTreeSet::new(), ..self.clone()} == Checkpoint::new(config)
+    }
+
+    /// Reads the checkpoint at `path`, or returns `None` if there is none yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Checkpoint>, CheckpointError>{
+        match fs::read(path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the checkpoint as JSON next to `path` and renames it into place, so a crash while
+    /// saving keeps the previous checkpoint intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError>{
+        let path = path.as_ref();
+        let partial = partial_path(path);
+        fs::write(&partial, serde_json::to_vec(self)?)?;
+        fs::rename(&partial, path)?;
+        Ok(())
+    }
+}
+
+/// Name of the manifest `run_atlas` keeps in the atlas directory.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// One written tile, as listed in the manifest.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ManifestEntry{
+    /// File name within the atlas directory.
+    pub file:       String,
+    pub rect:       Rect<f32>,
+    pub resolution: Resolution,
+    pub limit:      EscapeLimit,
+    /// Darkest and brightest gray levels in the tile.
+    pub min:        u8,
+    pub max:        u8,
+}
+
+/// Index of every tile in an atlas directory, so tools can find tiles without parsing file names.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Manifest{
+    /// Sorted by file name.
+    pub tiles: Vec<ManifestEntry>,
+}
+
+#[derive(Debug)]
+pub enum ManifestError{
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for ManifestError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            ManifestError::Io(err) => write!(f, "could not access manifest: {err}"),
+            ManifestError::Json(err) => write!(f, "invalid manifest: {err}"),
+        }
+    }
+}
+
+impl Error for ManifestError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            ManifestError::Io(err) => Some(err),
+            ManifestError::Json(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for ManifestError{
+    fn from(err: io::Error) -> Self{
+        ManifestError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ManifestError{
+    fn from(err: serde_json::Error) -> Self{
+        ManifestError::Json(err)
+    }
+}
+
+impl Manifest{
+    /// Reads the manifest at `path`, or returns an empty one if there is none yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Manifest, ManifestError>{
+        match fs::read(path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Manifest::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the manifest as pretty-printed JSON, renaming it into place like `Checkpoint::save`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ManifestError>{
+        let path = path.as_ref();
+        let partial = partial_path(path);
+        fs::write(&partial, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    /// Adds `entries`, replacing any listed under the same file name.
+    pub fn update(&mut self, entries: impl IntoIterator<Item = ManifestEntry>){
+        for entry in entries {
+            match self.tiles.binary_search_by(|tile| tile.file.cmp(&entry.file)) {
+                Ok(index) => self.tiles[index] = entry,
+                Err(index) => self.tiles.insert(index, entry),
+            }
+        }
+    }
+}
+
+/// How many items `Budgeted` yields between looks at the clock.
+const BUDGET_CHECK_INTERVAL: u32 = 1024;
+
+/// Iterator adapter that stops early once its time budget is spent, so one slow tile cannot hold a
+/// worker indefinitely. `expired` then tells a finished tile from an abandoned one.
+pub struct Budgeted<I>{
+    inner:    I,
+    deadline: Option<Instant>,
+    count:    u32,
+    expired:  bool,
+}
+
+impl<I> Budgeted<I>{
+    /// `budget = None` never expires.
+    pub fn new(inner: I, budget: Option<Duration>) -> Self{
+        Budgeted{inner, deadline: budget.map(|budget| Instant::now() + budget), count: 0, expired: false}
+    }
+
+    pub fn expired(&self) -> bool{
+        self.expired
+    }
+}
+
+impl<I: Iterator> Iterator for Budgeted<I>{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item>{
+        if self.expired {
+            return None;
+        }
+        if let Some(deadline) = self.deadline {
+            self.count += 1;
+            if self.count.is_multiple_of(BUDGET_CHECK_INTERVAL) && Instant::now() >= deadline {
+                self.expired = true;
+                return None;
+            }
+        }
+        self.inner.next()
+    }
+}
+
+/// Samples per side of the coarse grid `tile_interest` looks at.
+const INTEREST_SAMPLES: u32 = 9;
+
+/// Cheap estimate of how much boundary `rect` contains: the number of neighbouring pairs in a coarse
+/// grid of samples whose escape times differ, counting points inside the set as `limit`. Solid interior
+/// and far exterior tiles score zero or close to it.
+///
+/// With `prioritize`, a tile across the boundary therefore starts before one inside the set.
+///
+/// ```
+/// use mandelbrot::atlas::tile_interest;
+/// use mandelbrot::compute::EscapeParams;
+/// use mandelbrot::geometry::{Rect, Resolution};
+///
+/// let params = EscapeParams::default();
+/// let interior = Rect::from_bounds(-0.3, 0.1, -0.1, 0.1).unwrap();
+/// let boundary = Rect::from_bounds(0.1, 0.5, -0.1, 0.1).unwrap();
+/// assert_eq!(tile_interest(interior, 256, params), 0);
+/// assert!(tile_interest(boundary, 256, params) > 0);
+/// ```
+pub fn tile_interest(rect: Rect<f32>, limit: EscapeLimit, params: EscapeParams) -> u32{
+    let resolution = Resolution{width: INTEREST_SAMPLES, height: INTEREST_SAMPLES};
+    let precision = Precision::for_rect(&rect, &resolution);
+    // A tile collapsed to a line has no boundary to show.
+    let Ok(rows) = make_calculations_by_row(resolution, rect, limit, precision, params) else {
+        return 0;
+    };
+    let rows: Vec<Vec<EscapeLimit>> = rows
+        .map(|(_, row)| row.into_iter().map(|escape_time| escape_time.unwrap_or(limit)).collect())
+        .collect();
+
+    let across: usize = rows.iter()
+        .map(|row| row.windows(2).filter(|pair| pair[0] != pair[1]).count())
+        .sum();
+    let down: usize = rows.windows(2)
+        .map(|pair| pair[0].iter().zip(&pair[1]).filter(|(above, below)| above != below).count())
+        .sum();
+    (across + down) as u32
+}
+
+/// Samples per side of the coarse grid `uniform_escape_time` looks at inside the tile.
+const UNIFORM_SAMPLES: u32 = 16;
+
+/// Widest spread of escape times, in iterations, that still counts as a uniform exterior.
+const UNIFORM_SPREAD: EscapeLimit = 2;
+
+/// The escape time every pixel of a `resolution` tile over `rect` can be filled with, if its border
+/// pixels and a coarse grid inside all agree: `Some(None)` when they are all inside the set, or the
+/// rounded mean when they all escape within `UNIFORM_SPREAD` iterations of each other. Filaments too
+/// thin to hit a sample are missed, so the fill is only an approximation.
+///
+/// ```
+/// use mandelbrot::atlas::uniform_escape_time;
+/// use mandelbrot::compute::{EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+///
+/// let resolution = Resolution{width: 64, height: 64};
+/// let params = EscapeParams::default();
+/// let interior = Rect::from_bounds(-0.3, -0.1, -0.1, 0.1).unwrap();
+/// assert_eq!(uniform_escape_time(resolution, interior, 256, Precision::Single, params), Some(None));
+///
+/// let boundary = Rect::from_bounds(-0.8, -0.7, 0.05, 0.15).unwrap();
+/// assert_eq!(uniform_escape_time(resolution, boundary, 256, Precision::Single, params), None);
+/// ```
+pub fn uniform_escape_time(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams)
+                     -> Option<Option<EscapeLimit>> {
+    rect.validate().ok()?;
+    let Resolution{width, height} = resolution;
+    let (last_x, last_y) = (width.checked_sub(1)?, height.checked_sub(1)?);
+    let border = (0..width).flat_map(|x| [(x, 0), (x, last_y)])
+        .chain((0..height).flat_map(|y| [(0, y), (last_x, y)]));
+    let spread = |last: u32, i: u32| (last as u64 * i as u64 / (UNIFORM_SAMPLES - 1) as u64) as u32;
+    let grid = (0..UNIFORM_SAMPLES)
+        .flat_map(|j| (0..UNIFORM_SAMPLES).map(move |i| (i, j)))
+        .map(|(i, j)| (spread(last_x, i), spread(last_y, j)));
+
+    let mapper = coordinate_mapper(resolution, rect, precision, params.orientation);
+    let mut samples = border.chain(grid)
+        .map(|(x, y)| escape_time_at(mapper(x as f64, y as f64), limit, precision, params));
+
+    match samples.next()? {
+        None => samples.all(|escape_time| escape_time.is_none()).then_some(None),
+        Some(first) => {
+            let (mut low, mut high, mut sum, mut count) = (first, first, first as u64, 1_u64);
+            for escape_time in samples {
+                let escape_time = escape_time?;
+                low = low.min(escape_time);
+                high = high.max(escape_time);
+                if high - low > UNIFORM_SPREAD {
+                    return None;
+                }
+                sum += escape_time as u64;
+                count += 1;
+            }
+            Some(Some(((sum + count / 2) / count) as EscapeLimit))
+        }
+    }
+}
+
+/// Everything `run_atlas` needs to render a grid of `cols * rows` tiles over `region` into `dir`.
+#[derive(Clone, Debug)]
+pub struct AtlasConfig{
+    pub region:       Rect<f32>,
+    /// Tiles across the region, left to right.
+    pub cols:         u32,
+    /// Tiles up the region, bottom to top.
+    pub rows:         u32,
+    /// Size of every tile.
+    pub resolution:   Resolution,
+    pub limit:        EscapeLimit,
+    /// Scales `limit` per tile by the tile's width instead of using it as is.
+    pub adaptive_limit: Option<AdaptiveLimit>,
+    pub dir:          PathBuf,
+    /// `tile_is_interesting` threshold below which tiles are skipped.
+    pub min_contrast: u8,
+    /// `tile_is_mixed` fraction below which tiles are skipped, if any.
+    pub min_mixed:    Option<f32>,
+    /// Re-renders tiles that already exist.
+    pub force:        bool,
+    /// Abandons tiles taking longer than this.
+    pub tile_timeout: Option<Duration>,
+    pub normalize:    bool,
+    pub gamma:        f32,
+    /// Scores every tile with `tile_interest` first and starts the highest scoring ones first, so the
+    /// boundary shows up early in a long run.
+    pub prioritize:   bool,
+    /// Fills tiles that `uniform_escape_time` finds uniform with a single value instead of computing
+    /// every pixel. Much faster over solid interior and far exterior, but can miss thin filaments.
+    pub fill_uniform: bool,
+    /// JSON file recording finished tiles, read at the start and updated after every tile. Tiles it
+    /// lists are not rendered again unless `force` is set.
+    pub checkpoint:   Option<PathBuf>,
+    /// Once set, tiles that have not started yet are left out, while those in flight still finish.
+    pub cancel:       Option<Arc<AtomicBool>>,
+    /// JSON file listing every written tile, brought up to date at the end of the run.
+    pub manifest:     Option<PathBuf>,
+    /// Renders on a pool of this many threads instead of rayon's global one, which uses every core.
+    pub threads:      Option<usize>,
+    /// Extra pixels every tile computes past each edge of its rect, shared with its neighbours, for
+    /// `stitch_tiles` to crop away. The tile images grow by twice this each way.
+    pub overlap:      u32,
+}
+
+impl AtlasConfig{
+    /// Renders a square grid of `tiles` by `tiles` tiles at `limit` iterations and keeps those passing
+    /// `DEFAULT_MIN_CONTRAST`, with every other option off.
+    pub fn new(region: Rect<f32>, tiles: u32, resolution: Resolution, limit: EscapeLimit, dir: impl Into<PathBuf>) -> Self{
+        AtlasConfig{
+            region, resolution, limit,
+            cols:         tiles,
+            rows:         tiles,
+            adaptive_limit: None,
+            dir:          dir.into(),
+            min_contrast: DEFAULT_MIN_CONTRAST,
+            min_mixed:    None,
+            force:        false,
+            tile_timeout: None,
+            normalize:    false,
+            gamma:        1.0,
+            prioritize:   false,
+            fill_uniform: false,
+            checkpoint:   None,
+            cancel:       None,
+            manifest:     None,
+            threads:      None,
+            overlap:      0,
+        }
+    }
+
+    /// Rect of the tile in column `x` and row `y`, counted from the bottom left of the region. The
+    /// columns split the region's width and the rows its height, each into equal parts.
+    ///
+    /// ```
+    /// use mandelbrot::atlas::AtlasConfig;
+    /// use mandelbrot::geometry::{Rect, Resolution};
+    ///
+    /// let region = Rect::from_bounds(-2.0, 1.2, -0.9, 0.9).unwrap();
+    /// let config = AtlasConfig{cols: 16, rows: 9, ..AtlasConfig::new(region, 1, Resolution{width: 8, height: 8}, 32, "atlas")};
+    /// for x in 0..16 {
+    ///     for y in 0..9 {
+    ///         let rect = config.tile_rect(x, y);
+    ///         assert!((rect.x.span() - 0.2).abs() < 1e-6 && (rect.y.span() - 0.2).abs() < 1e-6);
+    ///         assert!((rect.x.min - (-2.0 + 0.2 * x as f32)).abs() < 1e-6);
+    ///         assert!((rect.y.min - (-0.9 + 0.2 * y as f32)).abs() < 1e-6);
+    ///     }
+    /// }
+    /// assert_eq!(config.tile_rect(15, 8).x.max, 1.2);
+    /// assert_eq!(config.tile_rect(15, 8).y.max, 0.9);
+    /// ```
+    pub fn tile_rect(&self, x: u32, y: u32) -> Rect<f32>{
+        tile_rect(self.region, self.cols, self.rows, x, y)
+    }
+
+    /// Iteration limit and parameters the tile over `rect` is rendered with, and so scored with when
+    /// `prioritize` is set.
+    fn tile_escape_settings(&self, rect: Rect<f32>) -> (EscapeLimit, EscapeParams){
+        let limit = match self.adaptive_limit {
+            Some(adaptive) => adaptive.limit_for(rect.x.span()),
+            None => self.limit,
+        };
+        (limit, EscapeParams::default())
+    }
+}
+
+/// Rect of the tile in column `x` and row `y` of a `cols` by `rows` atlas over `region`, exactly as
+/// `run_atlas` renders it, for rendering one tile again on its own.
+///
+/// Neighbouring tiles share their edges exactly, so the grid covers the region without gaps or
+/// overlaps:
+///
+/// ```
+/// use mandelbrot::atlas::tile_rect;
+/// use mandelbrot::geometry::Rect;
+///
+/// let region = Rect::from_bounds(-1.7, 0.6, -1.13, 0.97).unwrap();
+/// for n in [1, 2, 3, 7, 10] {
+///     for i in 0..n {
+///         let (column, row) = (tile_rect(region, n, n, i, 0), tile_rect(region, n, n, 0, i));
+///         assert!(column.x.min < column.x.max && row.y.min < row.y.max);
+///         if i + 1 < n {
+///             assert_eq!(column.x.max, tile_rect(region, n, n, i + 1, 0).x.min);
+///             assert_eq!(row.y.max, tile_rect(region, n, n, 0, i + 1).y.min);
+///         }
+///         for j in 0..n {
+///             assert_eq!(tile_rect(region, n, n, i, j).x, column.x);
+///             assert_eq!(tile_rect(region, n, n, j, i).y, row.y);
+///         }
+///     }
+///     assert_eq!(tile_rect(region, n, n, 0, 0).x.min, region.x.min);
+///     assert_eq!(tile_rect(region, n, n, 0, 0).y.min, region.y.min);
+///     assert_eq!(tile_rect(region, n, n, n - 1, n - 1).x.max, region.x.max);
+///     assert_eq!(tile_rect(region, n, n, n - 1, n - 1).y.max, region.y.max);
+/// }
+/// ```
+pub fn tile_rect(region: Rect<f32>, cols: u32, rows: u32, x: u32, y: u32) -> Rect<f32>{
+    Rect{
+        x: Range{min: tile_edge(region.x, cols, x), max: tile_edge(region.x, cols, x + 1)},
+        y: Range{min: tile_edge(region.y, rows, y), max: tile_edge(region.y, rows, y + 1)},
+    }
+}
+
+/// Edge `i` of `tiles` equal parts of `range`. The last one is the range's own end, which the lerp
+/// can miss by a rounding error.
+fn tile_edge(range: Range<f32>, tiles: u32, i: u32) -> f32{
+    if i >= tiles {
+        return range.max;
+    }
+    make_lerp((0.0, tiles as f32), (range.min, range.max))(i as f32)
+}
+
+/// What happened to one tile of the atlas.
+#[derive(Debug)]
+pub enum TileOutcome{
+    Written,
+    /// Rendered but too uniform to keep.
+    Skipped,
+    /// A previous run already wrote it.
+    AlreadyDone,
+    /// Abandoned once it took longer than `tile_timeout`.
+    TimedOut,
+    /// Never started because the run was cancelled.
+    Cancelled,
+    Failed(SaveError),
+}
+
+/// Totals over an atlas run. Iterations count pixels inside the set as `limit`. Every tile ends up in
+/// exactly one of the outcome counts.
+#[derive(Debug, Default)]
+pub struct AtlasStats{
+    pub total_tiles:      u64,
+    pub written:          u64,
+    pub skipped:          u64,
+    pub already_done:     u64,
+    pub timed_out:        u64,
+    pub cancelled:        u64,
+    pub failures:         Vec<(PathBuf, SaveError)>,
+    /// The first error reading or writing the checkpoint, if any. The run carries on without it.
+    pub checkpoint_error: Option<CheckpointError>,
+    /// Tiles written by this run, in no particular order.
+    pub manifest:         Vec<ManifestEntry>,
+    /// The error updating the manifest at the end, if any.
+    pub manifest_error:   Option<ManifestError>,
+    pub pixels_computed:  u64,
+    /// Pixels inside the set over every rendered tile, kept or skipped, filled or computed.
+    pub pixels_inside:    u64,
+    pub total_iterations: u64,
+    pub elapsed:          Duration,
+}
+
+impl AtlasStats{
+    pub fn average_iterations(&self) -> f64{
+        self.total_iterations as f64 / self.pixels_computed.max(1) as f64
+    }
+
+    fn merge(mut self, other: AtlasStats) -> AtlasStats{
+        self.total_tiles += other.total_tiles;
+        self.written += other.written;
+        self.skipped += other.skipped;
+        self.already_done += other.already_done;
+        self.timed_out += other.timed_out;
+        self.cancelled += other.cancelled;
+        self.failures.extend(other.failures);
+        self.checkpoint_error = self.checkpoint_error.or(other.checkpoint_error);
+        self.manifest.extend(other.manifest);
+        self.manifest_error = self.manifest_error.or(other.manifest_error);
+        self.pixels_computed += other.pixels_computed;
+        self.pixels_inside += other.pixels_inside;
+        self.total_iterations += other.total_iterations;
+        self
+    }
+}
+
+/// Renders, filters and saves one tile, returning its outcome with its share of the totals: the pixels
+/// and iterations spent, the pixels inside the set and, once written, its manifest entry.
+fn render_atlas_tile(config: &AtlasConfig, rect: Rect<f32>, path: &Path) -> (TileOutcome, AtlasStats){
+    if config.cancel.as_ref().is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+        return (TileOutcome::Cancelled, AtlasStats::default());
+    }
+    if !config.force && tile_is_done(path) {
+        return (TileOutcome::AlreadyDone, AtlasStats::default());
+    }
+
+    let resolution = config.resolution;
+    let (limit, params) = config.tile_escape_settings(rect);
+    let overlap = config.overlap;
+    let extended = Resolution{width: resolution.width + 2 * overlap, height: resolution.height + 2 * overlap};
+    let mut image = Image::new(&extended);
+    let metadata = RenderMetadata{rect, resolution, limit, kind: params.kind, overlap};
+    let precision = Precision::for_rect(&rect, &resolution);
+    // Tiles of a region too narrow to split this finely collapse to a line, with nothing to show.
+    let Ok(calculations) = make_calculations_with_overlap(resolution, rect, overlap, limit, precision, params) else {
+        return (TileOutcome::Skipped, AtlasStats::default());
+    };
+    let fill = config.fill_uniform
+        .then(|| uniform_escape_time(resolution, rect, limit, precision, params))
+        .flatten();
+    let calculations: Box<dyn Iterator<Item = ((u32, u32), Option<EscapeLimit>)>> = match fill {
+        Some(escape_time) => {
+            let width = extended.width;
+            Box::new((0..extended.height).flat_map(move |y| (0..width).map(move |x| ((x, y), escape_time))))
+        }
+        None => Box::new(calculations),
+    };
+    let (mut inside, mut pixels, mut iterations) = (0, 0, 0);
+    let calculations = calculations
+        .inspect(|(_, escape_time)| {
+            // Filled pixels were never computed, so only count them towards the inside fraction.
+            if fill.is_none() {
+                pixels += 1;
+                iterations += escape_time.unwrap_or(limit) as u64;
+            }
+            if escape_time.is_none() { inside += 1 }
+        });
+    let mut calculations = Budgeted::new(calculations, config.tile_timeout);
+    let total = if config.normalize {
+        let grid = EscapeGrid::collect(&extended, &mut calculations).expect("calculations match the image resolution");
+        write_normalized_data(&mut image, &grid).expect("grid matches the image resolution")
+    } else {
+        write_data(&mut image, &mut calculations, limit, config.gamma).expect("calculations match the image resolution")
+    };
+    let expired = calculations.expired();
+    drop(calculations);
+    let stats = AtlasStats{pixels_computed: pixels, total_iterations: iterations, pixels_inside: inside as u64, ..AtlasStats::default()};
+    if expired {
+        return (TileOutcome::TimedOut, stats);
+    }
+
+    let mixed = config.min_mixed.is_none_or(|fraction| tile_is_mixed(inside, total, fraction));
+    if !(mixed && tile_is_interesting(&image.data, config.min_contrast)) {
+        return (TileOutcome::Skipped, stats);
+    }
+    if let Err(err) = save_tile(&image, path, &metadata) {
+        return (TileOutcome::Failed(err), stats);
+    }
+    let entry = ManifestEntry{
+        file: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        rect, resolution, limit,
+        min: image.data.iter().copied().min().unwrap_or(0),
+        max: image.data.iter().copied().max().unwrap_or(0),
+    };
+    (TileOutcome::Written, AtlasStats{manifest: vec![entry], ..stats})
+}
+
+/// Renders every tile of the atlas in parallel, calling `on_tile` with each tile's path and outcome as
+/// it finishes, and returns the totals. The directory must already exist. With `threads` set, the
+/// tiles are rendered on a pool of their own, or on the global pool should it fail to start. Once
+/// `cancel` is set no further tile starts.
+///
+/// ```
+/// use mandelbrot::atlas::{run_atlas, AtlasConfig};
+/// use mandelbrot::geometry::{Rect, Resolution};
+///
+/// let region = Rect::from_bounds(-2.0, 1.0, -1.5, 1.5).unwrap();
+/// let dir = std::env::temp_dir().join("mandelbrot-atlas-doctest");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let config = AtlasConfig{force: true, ..AtlasConfig::new(region, 2, Resolution{width: 32, height: 32}, 64, &dir)};
+/// let stats = run_atlas(&config, |path, outcome| println!("{}: {outcome:?}", path.display()));
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// assert_eq!(stats.total_tiles, 4);
+/// ```
+pub fn run_atlas(config: &AtlasConfig, on_tile: impl Fn(&Path, &TileOutcome) + Sync) -> AtlasStats{
+    let pool = config.threads.and_then(|threads| ThreadPoolBuilder::new().num_threads(threads).build().ok());
+    match pool {
+        Some(pool) => pool.install(|| run_atlas_tiles(config, &on_tile)),
+        None => run_atlas_tiles(config, &on_tile),
+    }
+}
+
+fn run_atlas_tiles(config: &AtlasConfig, on_tile: &(impl Fn(&Path, &TileOutcome) + Sync)) -> AtlasStats{
+    let start = Instant::now();
+    let (cols, rows) = (config.cols, config.rows);
+
+    let mut positions: Vec<(u32, u32)> = (0..cols).flat_map(|x| (0..rows).map(move |y| (x, y))).collect();
+    if config.prioritize {
+        let mut scored: Vec<(u32, (u32, u32))> = positions
+            .par_iter()
+            .map(|&(x_i, y_i)| {
+                let rect = config.tile_rect(x_i, y_i);
+                let (limit, params) = config.tile_escape_settings(rect);
+                (tile_interest(rect, limit, params), (x_i, y_i))
+            })
+            .collect();
+        scored.sort_by_key(|&(score, _)| Reverse(score));
+        positions = scored.into_iter().map(|(_, position)| position).collect();
+    }
+
+    let mut checkpoint_error = None;
+    let checkpoint = config.checkpoint.as_ref().map(|path| {
+        let loaded = Checkpoint::load(path).unwrap_or_else(|err| {
+            checkpoint_error = Some(err);
+            None
+        });
+        Mutex::new(loaded.filter(|checkpoint| checkpoint.matches(config)).unwrap_or_else(|| Checkpoint::new(config)))
+    });
+    // Saves go through `try_lock` on a snapshot, so no worker waits on another's disk write; a worker
+    // finding a save in progress leaves its tiles to the next save or the final one.
+    let saved = Mutex::new(0_usize);
+    let save_checkpoint = |path: &Path, snapshot: Checkpoint| -> Option<CheckpointError> {
+        let mut saved = saved.try_lock().ok()?;
+        if snapshot.completed.len() <= *saved {
+            return None;
+        }
+        *saved = snapshot.completed.len();
+        snapshot.save(path).err()
+    };
+    let is_checkpointed = |position| {
+        checkpoint.as_ref().is_some_and(|checkpoint| checkpoint.lock().unwrap().completed.contains(&position))
+    };
+
+    // Bridged rather than split, so workers pick tiles up roughly in the order given.
+    let stats = positions
+        .into_iter()
+        .par_bridge()
+        .map(|(x_i, y_i)|{
+            let rect = config.tile_rect(x_i, y_i);
+
+            let path = config.dir.join(tile_file_name(x_i, y_i, cols.max(rows)));
+            let (outcome, mut stats) = if !config.force && is_checkpointed((x_i, y_i)) {
+                (TileOutcome::AlreadyDone, AtlasStats::default())
+            } else {
+                render_atlas_tile(config, rect, &path)
+            };
+
+            stats.total_tiles = 1;
+            if let (Some(checkpoint), Some(checkpoint_path)) = (&checkpoint, &config.checkpoint)
+                && matches!(outcome, TileOutcome::Written | TileOutcome::Skipped | TileOutcome::AlreadyDone)
+            {
+                let snapshot = {
+                    let mut checkpoint = checkpoint.lock().unwrap();
+                    let due = checkpoint.completed.insert((x_i, y_i)) && checkpoint.completed.len() % CHECKPOINT_INTERVAL == 0;
+                    due.then(|| checkpoint.clone())
+                };
+                if let Some(snapshot) = snapshot {
+                    stats.checkpoint_error = save_checkpoint(checkpoint_path, snapshot);
+                }
+            }
+            on_tile(&path, &outcome);
+
+            match outcome {
+                TileOutcome::Written => stats.written += 1,
+                TileOutcome::Skipped => stats.skipped += 1,
+                TileOutcome::AlreadyDone => stats.already_done += 1,
+                TileOutcome::TimedOut => stats.timed_out += 1,
+                TileOutcome::Cancelled => stats.cancelled += 1,
+                TileOutcome::Failed(err) => stats.failures.push((path, err)),
+            }
+            stats
+        })
+        .reduce(AtlasStats::default, AtlasStats::merge);
+
+    let final_save_error = checkpoint
+        .zip(config.checkpoint.as_ref())
+        .and_then(|(checkpoint, path)| save_checkpoint(path, checkpoint.into_inner().unwrap()));
+
+    let manifest_error = config.manifest.as_ref().and_then(|path| {
+        let update = || {
+            let mut manifest = Manifest::load(path)?;
+            manifest.update(stats.manifest.iter().cloned());
+            manifest.save(path)
+        };
+        update().err()
+    });
+    AtlasStats{elapsed: start.elapsed(), checkpoint_error: checkpoint_error.or(stats.checkpoint_error).or(final_save_error), manifest_error, ..stats}
+}
+
+/// Finished atlas tiles in `dir`, in no particular order.
+pub fn list_tiles(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>>{
+    let mut tiles = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_tile = path.file_name().and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("tile_") && name.ends_with(".png"));
+        if is_tile && tile_is_done(&path) {
+            tiles.push(path);
+        }
+    }
+    Ok(tiles)
+}
+
+#[derive(Debug)]
+pub enum StitchError{
+    /// The tile does not say which rect it covers.
+    Metadata{path: PathBuf, source: MetadataError},
+    Decoding{path: PathBuf, source: ImageError},
+}
+
+impl fmt::Display for StitchError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            StitchError::Metadata{path, source} => write!(f, "{}: {source}", path.display()),
+            StitchError::Decoding{path, source} => write!(f, "could not decode {}: {source}", path.display()),
+        }
+    }
+}
+
+impl Error for StitchError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            StitchError::Metadata{source, ..} => Some(source),
+            StitchError::Decoding{source, ..} => Some(source),
+        }
+    }
+}
+
+/// Assembles `tiles` into a grayscale (`L8` or `La8`) overview of `region` at `resolution`, placing
+/// each tile by the rect in its metadata and sampling the nearest tile pixel. Only one tile is held in
+/// memory at a time, so the overview can be far smaller than the atlas. Parts no tile covers, such as
+/// skipped uniform tiles, stay black, or transparent in `La8`. Tiles rendered with an overlap are
+/// cropped to their rect.
+pub fn stitch_tiles(tiles: &[PathBuf], region: Rect<f32>, resolution: Resolution, format: PixelFormat) -> Result<Image, StitchError>{
+    debug_assert!(matches!(format, PixelFormat::L8 | PixelFormat::La8));
+    let mut canvas = Image::with_format(&resolution, format);
+    let (width, height) = (resolution.width as f64, resolution.height as f64);
+    let pixel_width  = region.x.span() as f64 / width;
+    let pixel_height = region.y.span() as f64 / height;
+
+    for path in tiles {
+        let metadata = read_metadata(path)
+            .map_err(|source| StitchError::Metadata{path: path.clone(), source})?;
+        let (rect, overlap) = (metadata.rect, metadata.overlap);
+        let tile = ::image::open(path)
+            .map_err(|source| StitchError::Decoding{path: path.clone(), source})?
+            .into_luma8();
+        if tile.width() <= 2 * overlap || tile.height() <= 2 * overlap {
+            continue;
+        }
+        // The overlap is cropped, leaving the pixels covering `rect`.
+        let (tile_width, tile_height) = ((tile.width() - 2 * overlap) as f64, (tile.height() - 2 * overlap) as f64);
+
+        // Canvas pixels whose centers fall inside the tile.
+        let first_x = (((rect.x.min - region.x.min) as f64 / pixel_width - 0.5).ceil().max(0.0)) as u32;
+        let last_x  = (((rect.x.max - region.x.min) as f64 / pixel_width - 0.5).ceil().clamp(0.0, width)) as u32;
+        let first_y = (((region.y.max - rect.y.max) as f64 / pixel_height - 0.5).ceil().max(0.0)) as u32;
+        let last_y  = (((region.y.max - rect.y.min) as f64 / pixel_height - 0.5).ceil().clamp(0.0, height)) as u32;
+
+        for y in first_y..last_y {
+            let im = region.y.max as f64 - (y as f64 + 0.5) * pixel_height;
+            let tile_y = ((rect.y.max as f64 - im) / rect.y.span() as f64 * tile_height) as u32;
+            for x in first_x..last_x {
+                let re = region.x.min as f64 + (x as f64 + 0.5) * pixel_width;
+                let tile_x = ((re - rect.x.min as f64) / rect.x.span() as f64 * tile_width) as u32;
+                let value = tile.get_pixel(overlap + tile_x.min(tile_width as u32 - 1), overlap + tile_y.min(tile_height as u32 - 1)).0[0];
+                let index = x as usize + y as usize * resolution.width as usize;
+                match format {
+                    PixelFormat::La8 => canvas.data[index * 2..index * 2 + 2].copy_from_slice(&[value, u8::MAX]),
+                    _ => canvas.data[index] = value,
+                }
+            }
+        }
+    }
+
+    Ok(canvas)
+}
+
+#[cfg(test)]
+mod tests{
+    use std::time::Duration;
+
+    use super::*;
+    use crate::compute::make_calculations;
+
+    /// Fresh directory under the system temp dir, removed again on drop so a failed assert leaves
+    /// nothing behind.
+    struct TempDir(PathBuf);
+
+    impl TempDir{
+        fn new(name: &str) -> Self{
+            let path = std::env::temp_dir().join(format!("mandelbrot-atlas-test-{name}"));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn config(&self, region: Rect<f32>, tiles: u32, resolution: Resolution, limit: EscapeLimit) -> AtlasConfig{
+            AtlasConfig{force: true, ..AtlasConfig::new(region, tiles, resolution, limit, &self.0)}
+        }
+    }
+
+    impl Drop for TempDir{
+        fn drop(&mut self){
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn region() -> Rect<f32>{
+        Rect::from_bounds(-2.0, 1.0, -1.5, 1.5).unwrap()
+    }
+
+    fn outcomes(config: &AtlasConfig) -> (AtlasStats, Vec<String>){
+        let outcomes = Mutex::new(Vec::new());
+        let stats = run_atlas(config, |_, outcome| outcomes.lock().unwrap().push(format!("{outcome:?}")));
+        (stats, outcomes.into_inner().unwrap())
+    }
+
+    #[test]
+    fn finished_tiles_are_kept_unless_forced(){
+        let dir = TempDir::new("already-done");
+        let path = dir.0.join(tile_file_name(0, 0, 1));
+        fs::write(&path, b"from an earlier run").unwrap();
+
+        let config = AtlasConfig{force: false, min_contrast: 0, ..dir.config(region(), 1, Resolution{width: 16, height: 16}, 64)};
+        assert_eq!(outcomes(&config).1, ["AlreadyDone"]);
+        assert_eq!(fs::read(&path).unwrap(), b"from an earlier run");
+
+        assert_eq!(outcomes(&AtlasConfig{force: true, ..config}).1, ["Written"]);
+        assert!(::image::load_from_memory(&fs::read(&path).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn checkpoint_round_trips(){
+        let dir = TempDir::new("checkpoint");
+        let config = AtlasConfig{rows: 3, ..dir.config(region(), 4, Resolution{width: 64, height: 64}, 256)};
+        let mut checkpoint = Checkpoint::new(&config);
+        checkpoint.completed.extend([(0, 1), (3, 2)]);
+        let path = dir.0.join(CHECKPOINT_FILE_NAME);
+        checkpoint.save(&path).unwrap();
+
+        let loaded = Checkpoint::load(&path).unwrap().unwrap();
+        assert_eq!(loaded, checkpoint);
+        assert!(loaded.matches(&config));
+        assert!(!loaded.matches(&AtlasConfig{limit: 512, ..config.clone()}));
+        assert!(!loaded.matches(&AtlasConfig{overlap: 2, ..config}));
+    }
+
+    #[test]
+    fn checkpoint_from_another_run_is_ignored(){
+        let dir = TempDir::new("checkpoint-mismatch");
+        let path = dir.0.join(CHECKPOINT_FILE_NAME);
+        let config = AtlasConfig{
+            force: false, min_contrast: 0, checkpoint: Some(path.clone()),
+            ..dir.config(region(), 1, Resolution{width: 16, height: 16}, 64)
+        };
+        let mut checkpoint = Checkpoint::new(&config);
+        checkpoint.completed.insert((0, 0));
+
+        checkpoint.save(&path).unwrap();
+        assert_eq!(outcomes(&config).1, ["AlreadyDone"]);
+
+        fs::remove_file(&path).unwrap();
+        Checkpoint{limit: 128, ..checkpoint}.save(&path).unwrap();
+        assert_eq!(outcomes(&config).1, ["Written"]);
+        assert!(Checkpoint::load(&path).unwrap().unwrap().matches(&config));
+    }
+
+    #[test]
+    fn manifest_lists_written_tiles(){
+        let dir = TempDir::new("manifest");
+        let config = AtlasConfig{
+            manifest: Some(dir.0.join(MANIFEST_FILE_NAME)),
+            ..dir.config(region(), 4, Resolution{width: 24, height: 24}, 64)
+        };
+        let stats = run_atlas(&config, |_, _| {});
+        assert!(stats.skipped > 0 && stats.manifest_error.is_none());
+
+        let manifest = Manifest::load(dir.0.join(MANIFEST_FILE_NAME)).unwrap();
+        let mut written: Vec<_> = list_tiles(&dir.0).unwrap().iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        written.sort();
+        let listed: Vec<_> = manifest.tiles.iter().map(|entry| entry.file.clone()).collect();
+        assert_eq!(listed, written);
+        for entry in &manifest.tiles {
+            assert_eq!(read_metadata(dir.0.join(&entry.file)).unwrap().rect, entry.rect);
+            assert!(entry.min < entry.max);
+        }
+    }
+
+    fn render_order(config: &AtlasConfig) -> Vec<String>{
+        let order = Mutex::new(Vec::new());
+        run_atlas(config, |path, _| order.lock().unwrap().push(path.file_name().unwrap().to_str().unwrap().to_owned()));
+        order.into_inner().unwrap()
+    }
+
+    #[test]
+    fn prioritize_starts_with_the_boundary(){
+        let dir = TempDir::new("prioritize");
+        let region = Rect::from_bounds(-0.3, 0.5, -0.1, 0.1).unwrap();
+        let config = AtlasConfig{
+            cols: 2, rows: 1, prioritize: true, threads: Some(1),
+            ..dir.config(region, 1, Resolution{width: 16, height: 16}, 256)
+        };
+        assert_eq!(render_order(&config), [tile_file_name(1, 0, 2), tile_file_name(0, 0, 2)]);
+    }
+
+    #[test]
+    fn prioritize_scores_with_the_adaptive_limit(){
+        let dir = TempDir::new("prioritize-adaptive");
+        // At a limit of 1 both tiles score zero and keep their order; the adaptive limit the tiles
+        // render with shows the boundary.
+        let region = Rect::from_bounds(-0.3, 0.5, -0.1, 0.1).unwrap();
+        let config = AtlasConfig{
+            cols: 2, rows: 1, prioritize: true, threads: Some(1),
+            adaptive_limit: Some(AdaptiveLimit{base: 256, k: 0.0, max: 256}),
+            ..dir.config(region, 1, Resolution{width: 16, height: 16}, 1)
+        };
+        assert_eq!(render_order(&config), [tile_file_name(1, 0, 2), tile_file_name(0, 0, 2)]);
+    }
+
+    #[test]
+    fn grid_renders_every_tile(){
+        let dir = TempDir::new("grid");
+        let region = Rect::from_bounds(-2.0, 1.2, -0.9, 0.9).unwrap();
+        let config = AtlasConfig{cols: 16, rows: 9, min_contrast: 0, ..dir.config(region, 1, Resolution{width: 8, height: 8}, 32)};
+        let stats = run_atlas(&config, |_, _| {});
+        assert_eq!(stats.total_tiles, 144);
+        assert_eq!(stats.written + stats.skipped, 144);
+    }
+
+    #[test]
+    fn tiles_record_their_rect(){
+        let dir = TempDir::new("tile-rect");
+        let region = Rect::from_bounds(-2.0, 1.0, -1.2, 1.2).unwrap();
+        let config = AtlasConfig{cols: 5, rows: 3, min_contrast: 0, ..dir.config(region, 1, Resolution{width: 8, height: 8}, 32)};
+        run_atlas(&config, |_, _| {});
+        let tiles = list_tiles(&dir.0).unwrap();
+        assert_eq!(tiles.len(), 15);
+        for path in &tiles {
+            let name = path.file_stem().unwrap().to_str().unwrap();
+            let (x, y) = name.strip_prefix("tile_x").unwrap().split_once("_y").unwrap();
+            let rect = tile_rect(region, 5, 3, x.parse().unwrap(), y.parse().unwrap());
+            assert_eq!(read_metadata(path).unwrap().rect, rect);
+        }
+    }
+
+    #[test]
+    fn slow_tiles_time_out_unsaved(){
+        let dir = TempDir::new("timeout");
+        let region = Rect::from_bounds(-0.75, -0.74, 0.1, 0.11).unwrap();
+        let config = AtlasConfig{
+            tile_timeout: Some(Duration::from_micros(1)),
+            ..dir.config(region, 1, Resolution{width: 128, height: 128}, u16::MAX)
+        };
+        let (stats, outcomes) = outcomes(&config);
+        assert_eq!(outcomes, ["TimedOut"]);
+        assert_eq!(stats.timed_out, 1);
+        assert!(list_tiles(&dir.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn every_tile_has_one_outcome(){
+        let dir = TempDir::new("stats");
+        fs::write(dir.0.join(tile_file_name(0, 0, 2)), b"from an earlier run").unwrap();
+        let config = AtlasConfig{force: false, ..dir.config(region(), 2, Resolution{width: 32, height: 32}, 64)};
+        let stats = run_atlas(&config, |_, _| {});
+
+        assert_eq!(stats.total_tiles, 4);
+        assert_eq!(stats.already_done, 1);
+        let outcomes = stats.written + stats.skipped + stats.already_done + stats.timed_out + stats.cancelled
+            + stats.failures.len() as u64;
+        assert_eq!(outcomes, stats.total_tiles);
+    }
+
+    #[test]
+    fn thread_count_does_not_change_tiles(){
+        let render = |name, threads| {
+            let dir = TempDir::new(name);
+            let config = AtlasConfig{threads, ..dir.config(region(), 3, Resolution{width: 32, height: 32}, 64)};
+            let stats = run_atlas(&config, |_, _| {});
+            assert!(stats.written > 0 && stats.failures.is_empty());
+            let mut tiles: Vec<_> = fs::read_dir(&dir.0).unwrap().map(|entry| entry.unwrap().path()).collect();
+            tiles.sort();
+            tiles.iter().map(|path| fs::read(path).unwrap()).collect::<Vec<_>>()
+        };
+        assert_eq!(render("threads-1", Some(1)), render("threads-auto", None));
+    }
+
+    #[test]
+    fn cancelled_run_writes_nothing(){
+        let dir = TempDir::new("cancel");
+        let cancel = Arc::new(AtomicBool::new(true));
+        let config = AtlasConfig{cancel: Some(cancel), ..dir.config(region(), 3, Resolution{width: 16, height: 16}, 64)};
+        let stats = run_atlas(&config, |_, _| {});
+
+        assert_eq!(stats.total_tiles, 9);
+        assert_eq!(stats.cancelled, stats.total_tiles);
+        assert!(list_tiles(&dir.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn stitched_tiles_match_a_direct_render(){
+        let dir = TempDir::new("stitch");
+        let config = AtlasConfig{min_contrast: 0, ..dir.config(region(), 2, Resolution{width: 32, height: 32}, 64)};
+        run_atlas(&config, |_, _| {});
+        let tiles = list_tiles(&dir.0).unwrap();
+        assert_eq!(tiles.len(), 4);
+        let full = Resolution{width: 64, height: 64};
+        let stitched = stitch_tiles(&tiles, region(), full, PixelFormat::L8).unwrap();
+
+        let mut direct = Image::new(&full);
+        let data = make_calculations(full, region(), 64, Precision::Single, EscapeParams::default()).unwrap();
+        write_data(&mut direct, data, 64, 1.0).unwrap();
+        assert_eq!(stitched.data, direct.data);
+    }
+}
@@ -0,0 +1,477 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use num::Complex;
+
+use mandelbrot::color::{ColorScale, Dither, InteriorColoring, Light};
+use mandelbrot::compute::EscapeLimit;
+use mandelbrot::geometry::{Range, Rect, RectError, Resolution};
+use mandelbrot::image::{OutputFormat, PixelFormat, DEFAULT_MIN_CONTRAST};
+
+pub const USAGE: &str = "\
+Usage: mandelbrot [MODE] [OPTIONS]
+
+Modes:
+    atlas                   Split the region into a grid of tiles, one PNG each [default]
+    single                  Render the region into a single PNG
+    zoom                    Render numbered frames zooming from the region into a target
+    jobs                    Render every job listed in the --config file
+    stitch                  Assemble the atlas tiles in --output-dir into one --output image of
+                            --width x --height pixels
+    tile                    Render the atlas tile in column --x-index and row --y-index of the grid
+                            the region and --tiles, --cols or --rows make, as a single image
+    explore                 Open a window onto the region to recenter by clicking and zoom by
+                            scrolling, printing the last view on exit (needs a build with the
+                            interactive feature)
+
+Options:
+    --config <FILE>         TOML or JSON file of render jobs, for the jobs mode
+    --width <PIXELS>        Tile width in pixels [default: 8192]
+    --height <PIXELS>       Tile height in pixels [default: 8192]
+    --limit <ITERATIONS>    Escape iteration limit [default: 256]
+    --adaptive-limit <K>    Scale the limit of each atlas tile by 1 + K * log2(1 / tile width)
+    --max-limit <ITERATIONS> Cap on adaptively scaled limits [default: 65535]
+    --x-min <X>             Left edge of the rendered region [default: -2.0]
+    --x-max <X>             Right edge of the rendered region [default: 1.0]
+    --y-min <Y>             Bottom edge of the rendered region [default: -1.5]
+    --y-max <Y>             Top edge of the rendered region [default: 1.5]
+    --tiles <COUNT>         Atlas tiles along each side of the region [default: 128]
+    --x-index <I>           Column of the tile mode's tile, from 0 on the left
+    --y-index <J>           Row of the tile mode's tile, from 0 at the bottom
+    --cols <COUNT>          Atlas tiles across the region, overriding --tiles
+    --rows <COUNT>          Atlas tiles up the region, overriding --tiles
+    --output-dir <DIR>      Directory the atlas tiles or zoom frames are written to
+                            [default: atlas/ or frames/]
+    --output <FILE>         File the single image is written to [default: mandelbrot.png]
+    --gpu                   Compute the single image on the GPU, if one is found (needs a build with
+                            the gpu feature)
+    --progressive           Render the single image coarse to fine, saving it over --output after
+                            every pass from 1/16 of the resolution up, to watch it sharpen
+    --mmap <FILE>           Render the single image into a memory-mapped FILE instead of RAM and
+                            stream the PNG out of it, for images too large to hold in memory
+    --raw <FILE>            Also save the single image's escape counts as a NumPy .npy array
+    --normalized <FILE>     Also save the single image's smooth escape values divided by the limit as
+                            a NumPy .npy array of f32 in [0, 1], with -1 inside the set
+    --exr <FILE>            Also save the single image's smooth escape values as a 32-bit float
+                            OpenEXR image
+    --format <FORMAT>       Format of the single image, png, jpeg or bmp [default: from the
+                            --output extension, else png]
+    --quality <PERCENT>     JPEG quality from 1 to 100 [default: 90]
+    --bit-depth <BITS>      Gray levels of the single image, 8 or 16 [default: 8]
+    --frames <COUNT>        Number of zoom frames [default: 100]
+    --center-re <X>         Real part of the zoom target [default: -0.743643887]
+    --center-im <Y>         Imaginary part of the zoom target [default: 0.131825904]
+    --zoom <FACTOR>         Zoom of the last frame, 1 being the full set [default: 1000]
+    --min-contrast <LEVELS> Skip atlas tiles whose gray levels spread by no more than this [default: 20]
+    --min-mixed <FRACTION>  Also skip atlas tiles where the inside or the outside of the set covers
+                            less than this fraction of the pixels
+    --gamma <GAMMA>         Gamma applied to the gray levels, above 1 brightens mid-tones [default: 1.0]
+    --interior <COLORING>   Shading of the inside of the set in single images: black, a gray level
+                            from 0 to 255, magnitude or mean [default: black]
+    --scale <SCALE>         Curve of the single image's gray levels over the escape counts, linear,
+                            log or sqrt [default: linear]
+    --dither <DITHER>       Dither the single image's gray levels against banding, off, ordered or
+                            noise [default: off]
+    --dither-seed <SEED>    Seed of the dither pattern [default: 0]
+    --stripes <FREQUENCY>   Color the single image by stripe average coloring, the orbit's mean of
+                            sin(FREQUENCY * arg(z)), instead of by the escape counts (5 is typical)
+    --shade <AZIMUTH,ELEVATION> Light the single image's boundary distances as a relief, from a light
+                            AZIMUTH degrees counterclockwise from the right and ELEVATION degrees
+                            above the image (135,45 lights it from the top left)
+    --mipmaps               Also write the stitched image halved again and again, down to one pixel,
+                            as <OUTPUT>_mip1.png, <OUTPUT>_mip2.png and so on
+    --transparent           Leave the parts of a stitched image that no tile covers transparent
+    --normalize             Stretch the gray levels over the escape counts each image actually holds
+    --tile-timeout <SECONDS> Abandon atlas tiles that take longer than this [default: unlimited]
+    --force                 Re-render atlas tiles that already exist
+    --threads <N>           Render the atlas on N threads [default: one per core]
+    --overlap <PIXELS>      Render every atlas tile this many pixels past each edge, shared with its
+                            neighbours and cropped again by the stitch mode [default: 0]
+    --interesting-first     Render the atlas tiles with the most boundary first
+    --fill-uniform          Fill atlas tiles whose border and a coarse grid of samples are all inside
+                            or all outside the set with one value instead of computing every pixel.
+                            Much faster, but can miss thin filaments
+    -q, --quiet             Do not show progress
+    -h, --help              Print this message
+";
+
+#[derive(Debug)]
+pub enum ArgsError{
+    Help,
+    UnknownArgument(String),
+    MissingValue(String),
+    /// A mode was given without an option it cannot do without.
+    MissingOption{mode: &'static str, flag: &'static str},
+    Conflict(&'static str, &'static str),
+    InvalidValue{flag: String, value: String},
+    ZeroDimension(&'static str),
+    /// `--limit` or `--max-limit` is zero, which would leave every point inside the set.
+    ZeroLimit(&'static str),
+    InvalidRect(RectError),
+}
+
+impl fmt::Display for ArgsError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            ArgsError::Help => write!(f, "{USAGE}"),
+            ArgsError::UnknownArgument(arg) => write!(f, "unknown argument `{arg}`"),
+            ArgsError::MissingValue(flag) => write!(f, "`{flag}` expects a value"),
+            ArgsError::MissingOption{mode, flag} => write!(f, "the {mode} mode needs `{flag}`"),
+            ArgsError::Conflict(first, second) => write!(f, "`{first}` cannot be combined with `{second}`"),
+            ArgsError::InvalidValue{flag, value} => write!(f, "invalid value `{value}` for `{flag}`"),
+            ArgsError::ZeroDimension(name) => write!(f, "{name} must be greater than zero"),
+            ArgsError::ZeroLimit(flag) => write!(f, "`{flag}` must allow at least one iteration"),
+            ArgsError::InvalidRect(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for ArgsError{}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RenderMode{
+    Atlas,
+    Single,
+    Zoom,
+    Stitch,
+    Tile,
+    Jobs,
+    #[cfg(feature = "interactive")]
+    Explore,
+}
+
+pub struct Args{
+    pub mode:       RenderMode,
+    pub resolution: Resolution,
+    pub limit:      EscapeLimit,
+    pub adaptive_k: Option<f32>,
+    pub max_limit:  EscapeLimit,
+    pub rect:       Rect<f32>,
+    pub cols:       u32,
+    pub rows:       u32,
+    pub x_index:    Option<u32>,
+    pub y_index:    Option<u32>,
+    pub output_dir: Option<String>,
+    pub output:     String,
+    pub config:     Option<String>,
+    pub raw:        Option<String>,
+    pub exr:        Option<String>,
+    pub normalized: Option<String>,
+    pub mmap:       Option<PathBuf>,
+    pub gpu:        bool,
+    pub progressive: bool,
+    pub format:     PixelFormat,
+    pub output_format: Option<OutputFormat>,
+    pub quality:    Option<u8>,
+    pub quiet:      bool,
+    pub min_contrast: u8,
+    pub min_mixed:  Option<f32>,
+    pub force:      bool,
+    pub prioritize: bool,
+    pub fill_uniform: bool,
+    pub threads:    Option<usize>,
+    pub overlap:    u32,
+    pub tile_timeout: Option<Duration>,
+    pub normalize:  bool,
+    pub transparent: bool,
+    pub mipmaps:    bool,
+    pub scale:      ColorScale,
+    pub dither:     Dither,
+    pub dither_seed: u64,
+    pub stripes:    Option<f32>,
+    pub shade:      Option<Light>,
+    pub interior:   InteriorColoring,
+    pub gamma:      f32,
+    pub frames:     u32,
+    pub target:     Complex<f32>,
+    pub zoom:       f32,
+}
+
+impl Default for Args{
+    fn default() -> Self{
+        Args{
+            mode:       RenderMode::Atlas,
+            resolution: Resolution{width: 1024*2*2*2, height: 1024*2*2*2},
+            limit:      256,
+            adaptive_k: None,
+            max_limit:  EscapeLimit::MAX,
+            rect:       Rect{x: Range{min: -2.0, max: 1.0}, y: Range{min: -1.5, max: 1.5}},
+            cols:       128,
+            rows:       128,
+            x_index:    None,
+            y_index:    None,
+            output_dir: None,
+            output:     "mandelbrot.png".to_string(),
+            config:     None,
+            raw:        None,
+            exr:        None,
+            normalized: None,
+            mmap:       None,
+            gpu:        false,
+            progressive: false,
+            format:     PixelFormat::L8,
+            output_format: None,
+            quality:    None,
+            quiet:      false,
+            min_contrast: DEFAULT_MIN_CONTRAST,
+            min_mixed:  None,
+            force:      false,
+            prioritize: false,
+            fill_uniform: false,
+            threads:    None,
+            overlap:    0,
+            tile_timeout: None,
+            normalize:  false,
+            transparent: false,
+            mipmaps:    false,
+            scale:      ColorScale::Linear,
+            dither:     Dither::Off,
+            dither_seed: 0,
+            stripes:    None,
+            shade:      None,
+            interior:   InteriorColoring::default(),
+            gamma:      1.0,
+            frames:     100,
+            target:     Complex::<f32>{re: -0.743_643_9, im: 0.131_825_9},
+            zoom:       1000.0,
+        }
+    }
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, ArgsError>{
+    let value = value.ok_or_else(|| ArgsError::MissingValue(flag.to_string()))?;
+    value.parse().map_err(|_| ArgsError::InvalidValue{flag: flag.to_string(), value})
+}
+
+fn parse_coordinate(flag: &str, value: Option<String>) -> Result<f32, ArgsError>{
+    let raw = value.clone();
+    let coordinate: f32 = parse_value(flag, value)?;
+    if coordinate.is_finite() {
+        Ok(coordinate)
+    } else {
+        Err(ArgsError::InvalidValue{flag: flag.to_string(), value: raw.unwrap_or_default()})
+    }
+}
+
+fn parse_positive(flag: &str, value: Option<String>) -> Result<f32, ArgsError>{
+    let raw = value.clone();
+    let number = parse_coordinate(flag, value)?;
+    if number > 0.0 {
+        Ok(number)
+    } else {
+        Err(ArgsError::InvalidValue{flag: flag.to_string(), value: raw.unwrap_or_default()})
+    }
+}
+
+/// Positive seconds, short enough for a `Duration`.
+fn parse_duration(flag: &str, value: Option<String>) -> Result<Duration, ArgsError>{
+    let raw = value.clone();
+    let seconds = parse_positive(flag, value)?;
+    Duration::try_from_secs_f32(seconds).map_err(|_| ArgsError::InvalidValue{flag: flag.to_string(), value: raw.unwrap_or_default()})
+}
+
+fn parse_fraction(flag: &str, value: Option<String>) -> Result<f32, ArgsError>{
+    let raw = value.clone();
+    let fraction: f32 = parse_value(flag, value)?;
+    if (0.0..=0.5).contains(&fraction) {
+        Ok(fraction)
+    } else {
+        Err(ArgsError::InvalidValue{flag: flag.to_string(), value: raw.unwrap_or_default()})
+    }
+}
+
+fn parse_quality(flag: &str, value: Option<String>) -> Result<u8, ArgsError>{
+    let raw = value.clone();
+    let quality: u8 = parse_value(flag, value)?;
+    if (1..=100).contains(&quality) {
+        Ok(quality)
+    } else {
+        Err(ArgsError::InvalidValue{flag: flag.to_string(), value: raw.unwrap_or_default()})
+    }
+}
+
+fn parse_bit_depth(flag: &str, value: Option<String>) -> Result<PixelFormat, ArgsError>{
+    let raw = value.clone();
+    match parse_value(flag, value)? {
+        8 => Ok(PixelFormat::L8),
+        16 => Ok(PixelFormat::L16),
+        _ => Err(ArgsError::InvalidValue{flag: flag.to_string(), value: raw.unwrap_or_default()}),
+    }
+}
+
+impl Args{
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, ArgsError>{
+        let mut parsed = Args::default();
+        let mut args = args.into_iter().peekable();
+
+        match args.peek().map(String::as_str) {
+            Some("atlas")  => { args.next(); }
+            Some("single") => { args.next(); parsed.mode = RenderMode::Single; }
+            Some("zoom")   => { args.next(); parsed.mode = RenderMode::Zoom; }
+            Some("stitch") => { args.next(); parsed.mode = RenderMode::Stitch; }
+            Some("jobs")   => { args.next(); parsed.mode = RenderMode::Jobs; }
+            Some("tile")   => { args.next(); parsed.mode = RenderMode::Tile; }
+            #[cfg(feature = "interactive")]
+            Some("explore") => { args.next(); parsed.mode = RenderMode::Explore; }
+            _ => {}
+        }
+
+        while let Some(arg) = args.next() {
+            let (flag, mut inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg, None),
+            };
+            let mut value = || inline_value.take().or_else(|| args.next());
+
+            match flag.as_str() {
+                "-h" | "--help" => return Err(ArgsError::Help),
+                "-q" | "--quiet" => parsed.quiet = true,
+                "--force"      => parsed.force = true,
+                "--interesting-first" => parsed.prioritize = true,
+                "--fill-uniform" => parsed.fill_uniform = true,
+                "--threads"    => parsed.threads = Some(parse_value(&flag, value())?),
+                "--overlap"    => parsed.overlap = parse_value(&flag, value())?,
+                "--tile-timeout" => parsed.tile_timeout = Some(parse_duration(&flag, value())?),
+                "--normalize"  => parsed.normalize = true,
+                "--transparent" => parsed.transparent = true,
+                "--mipmaps"    => parsed.mipmaps = true,
+                "--scale"      => parsed.scale = parse_value(&flag, value())?,
+                "--dither"     => parsed.dither = parse_value(&flag, value())?,
+                "--dither-seed" => parsed.dither_seed = parse_value(&flag, value())?,
+                "--stripes"    => parsed.stripes = Some(parse_coordinate(&flag, value())?),
+                "--shade"      => parsed.shade = Some(parse_value(&flag, value())?),
+                "--interior"   => parsed.interior = parse_value(&flag, value())?,
+                "--gamma"      => parsed.gamma = parse_positive(&flag, value())?,
+                "--min-contrast" => parsed.min_contrast = parse_value(&flag, value())?,
+                "--min-mixed"  => parsed.min_mixed = Some(parse_fraction(&flag, value())?),
+                "--width"      => parsed.resolution.width = parse_value(&flag, value())?,
+                "--height"     => parsed.resolution.height = parse_value(&flag, value())?,
+                "--limit"      => parsed.limit = parse_value(&flag, value())?,
+                "--adaptive-limit" => parsed.adaptive_k = Some(parse_coordinate(&flag, value())?),
+                "--max-limit"  => parsed.max_limit = parse_value(&flag, value())?,
+                "--x-min"      => parsed.rect.x.min = parse_coordinate(&flag, value())?,
+                "--x-max"      => parsed.rect.x.max = parse_coordinate(&flag, value())?,
+                "--y-min"      => parsed.rect.y.min = parse_coordinate(&flag, value())?,
+                "--y-max"      => parsed.rect.y.max = parse_coordinate(&flag, value())?,
+                "--tiles"      => {
+                    let tiles = parse_value(&flag, value())?;
+                    (parsed.cols, parsed.rows) = (tiles, tiles);
+                }
+                "--x-index"    => parsed.x_index = Some(parse_value(&flag, value())?),
+                "--y-index"    => parsed.y_index = Some(parse_value(&flag, value())?),
+                "--cols"       => parsed.cols = parse_value(&flag, value())?,
+                "--rows"       => parsed.rows = parse_value(&flag, value())?,
+                "--output-dir" => parsed.output_dir = Some(parse_value(&flag, value())?),
+                "--frames"     => parsed.frames = parse_value(&flag, value())?,
+                "--center-re"  => parsed.target.re = parse_coordinate(&flag, value())?,
+                "--center-im"  => parsed.target.im = parse_coordinate(&flag, value())?,
+                "--zoom"       => parsed.zoom = parse_positive(&flag, value())?,
+                "--output"     => parsed.output = parse_value(&flag, value())?,
+                "--raw"        => parsed.raw = Some(parse_value(&flag, value())?),
+                "--exr"        => parsed.exr = Some(parse_value(&flag, value())?),
+                "--normalized" => parsed.normalized = Some(parse_value(&flag, value())?),
+                "--progressive" => parsed.progressive = true,
+                "--mmap"       => parsed.mmap = Some(parse_value(&flag, value())?),
+                #[cfg(feature = "gpu")]
+                "--gpu"        => parsed.gpu = true,
+                "--config"     => parsed.config = Some(parse_value(&flag, value())?),
+                "--bit-depth"  => parsed.format = parse_bit_depth(&flag, value())?,
+                "--format"     => parsed.output_format = Some(parse_value(&flag, value())?),
+                "--quality"    => parsed.quality = Some(parse_quality(&flag, value())?),
+                _ => return Err(ArgsError::UnknownArgument(flag)),
+            }
+        }
+
+        parsed.validate()?;
+        Ok(parsed)
+    }
+
+    fn validate(&self) -> Result<(), ArgsError>{
+        if self.resolution.width == 0 {
+            return Err(ArgsError::ZeroDimension("width"));
+        }
+        if self.resolution.height == 0 {
+            return Err(ArgsError::ZeroDimension("height"));
+        }
+        if self.limit == 0 {
+            return Err(ArgsError::ZeroLimit("--limit"));
+        }
+        if self.max_limit == 0 {
+            return Err(ArgsError::ZeroLimit("--max-limit"));
+        }
+        if self.mode == RenderMode::Jobs && self.config.is_none() {
+            return Err(ArgsError::MissingOption{mode: "jobs", flag: "--config"});
+        }
+        if self.mode == RenderMode::Tile {
+            for (index, count, flag) in [(self.x_index, self.cols, "--x-index"), (self.y_index, self.rows, "--y-index")] {
+                match index {
+                    None => return Err(ArgsError::MissingOption{mode: "tile", flag}),
+                    Some(index) if index >= count => {
+                        return Err(ArgsError::InvalidValue{flag: flag.to_string(), value: index.to_string()});
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        // Each option set, and the options it cannot be combined with. `--gpu` only computes the plain
+        // escape times, so it conflicts with every coloring that needs more.
+        let options: [(bool, &'static str, &[&'static str]); 11] = [
+            (self.normalize, "--normalize", &["--gpu"]),
+            (self.raw.is_some(), "--raw", &["--gpu"]),
+            (self.interior != InteriorColoring::default(), "--interior", &["--normalize", "--raw", "--gpu", "--bit-depth 16"]),
+            (self.scale != ColorScale::Linear, "--scale", &["--normalize", "--interior", "--gpu", "--bit-depth 16"]),
+            (self.dither != Dither::Off, "--dither", &["--normalize", "--interior", "--gpu", "--bit-depth 16"]),
+            (self.stripes.is_some(), "--stripes",
+                &["--normalize", "--raw", "--interior", "--scale", "--dither", "--gpu", "--bit-depth 16"]),
+            (self.shade.is_some(), "--shade",
+                &["--stripes", "--normalize", "--raw", "--interior", "--scale", "--dither", "--gpu", "--bit-depth 16"]),
+            (self.progressive, "--progressive", &["--stripes", "--shade", "--interior", "--gpu", "--mmap"]),
+            (self.gpu, "--gpu", &[]),
+            (self.mmap.is_some(), "--mmap", &[]),
+            (self.format != PixelFormat::L8, "--bit-depth 16", &[]),
+        ];
+        if let Some((option, conflict)) = find_conflict(&options) {
+            return Err(ArgsError::Conflict(option, conflict));
+        }
+        if self.mmap.is_some() && self.output_format() != OutputFormat::Png {
+            return Err(ArgsError::Conflict("--mmap", "non-PNG output"));
+        }
+        if self.cols == 0 {
+            return Err(ArgsError::ZeroDimension("cols"));
+        }
+        if self.rows == 0 {
+            return Err(ArgsError::ZeroDimension("rows"));
+        }
+        if self.threads == Some(0) {
+            return Err(ArgsError::ZeroDimension("threads"));
+        }
+        self.rect.validate().map_err(ArgsError::InvalidRect)
+    }
+
+    /// `--format`, or the format the `--output` extension names, with `--quality` applied to JPEG.
+    pub fn output_format(&self) -> OutputFormat{
+        let format = self.output_format
+            .or_else(|| OutputFormat::from_path(&self.output))
+            .unwrap_or(OutputFormat::Png);
+        match (format, self.quality) {
+            (OutputFormat::Jpeg{..}, Some(quality)) => OutputFormat::Jpeg{quality},
+            _ => format,
+        }
+    }
+
+    pub fn output_dir_or(&self, default: &str) -> String{
+        self.output_dir.clone().unwrap_or_else(|| default.to_string())
+    }
+}
+
+/// The first option set in `options` along with an option set that it lists as a conflict.
+fn find_conflict(options: &[(bool, &'static str, &[&'static str])]) -> Option<(&'static str, &'static str)>{
+    debug_assert!(options.iter().flat_map(|(_, _, conflicts)| *conflicts).all(|conflict| options.iter().any(|(_, option, _)| option == conflict)),
+        "every conflict has to be an option of the table");
+    let is_set = |name: &str| options.iter().any(|&(set, option, _)| set && option == name);
+    options.iter()
+        .filter(|(set, ..)| *set)
+        .find_map(|&(_, option, conflicts)| conflicts.iter().find(|conflict| is_set(conflict)).map(|&conflict| (option, conflict)))
+}
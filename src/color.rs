@@ -0,0 +1,1375 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::compute::{EscapeLimit, EscapeOutcome, InteriorData};
+use crate::geometry::{make_lerp, Resolution};
+use crate::grid::EscapeGrid;
+use crate::image::{Image, PixelFormat};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteError{
+    /// A calculated pixel lies outside the image, usually because the calculations were made for
+    /// a different resolution.
+    OutOfBounds{position: (u32, u32), width: u32, height: u32},
+    /// The coloring writes gray levels or colors, which this image's pixels do not hold.
+    UnsupportedFormat(PixelFormat),
+}
+
+impl fmt::Display for WriteError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            WriteError::OutOfBounds{position: (x, y), width, height} =>
+                write!(f, "pixel ({x}, {y}) is outside of the {width}x{height} image"),
+            WriteError::UnsupportedFormat(format) => write!(f, "cannot write this coloring into a {format:?} image"),
+        }
+    }
+}
+
+impl Error for WriteError{}
+
+pub(crate) fn pixel_index(resolution: &Resolution, position: (u32, u32)) -> Result<usize, WriteError>{
+    let Resolution{width, height} = *resolution;
+    if position.0 >= width || position.1 >= height {
+        return Err(WriteError::OutOfBounds{position, width, height});
+    }
+    // In `usize`, since `x + y * width` overflows `u32` past 4 gigapixels.
+    Ok(position.0 as usize + position.1 as usize * width as usize)
+}
+
+/// Fails unless `img` holds 8-bit gray levels, with or without alpha, for `put_gray`.
+fn check_gray(img: &Image) -> Result<(), WriteError>{
+    match img.format {
+        PixelFormat::L8 | PixelFormat::La8 => Ok(()),
+        format => Err(WriteError::UnsupportedFormat(format)),
+    }
+}
+
+/// Fails unless `img` holds 8-bit RGB colors, with or without alpha, for `put_rgb`.
+fn check_rgb(img: &Image) -> Result<(), WriteError>{
+    match img.format {
+        PixelFormat::Rgb8 | PixelFormat::Rgba8 => Ok(()),
+        format => Err(WriteError::UnsupportedFormat(format)),
+    }
+}
+
+/// Writes `color` into pixel `index` of an image `check_rgb` accepted, opaque in `Rgba8`.
+fn put_rgb(img: &mut Image, index: usize, color: [u8; 3]){
+    match img.format {
+        PixelFormat::Rgba8 => img.data[index * 4..index * 4 + 4].copy_from_slice(&[color[0], color[1], color[2], u8::MAX]),
+        _ => img.data[index * 3..index * 3 + 3].copy_from_slice(&color),
+    }
+}
+
+/// Writes `level` into pixel `index` of an image `check_gray` accepted, opaque in `La8`.
+fn put_gray(img: &mut Image, index: usize, level: u8){
+    match img.format {
+        PixelFormat::La8 => img.data[index * 2..index * 2 + 2].copy_from_slice(&[level, u8::MAX]),
+        _ => img.data[index] = level,
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadPaletteError{
+    Io(io::Error),
+    /// Line `line` (counting from 1) is not a valid stop or header.
+    Malformed{line: usize, content: String},
+    /// The file holds no color stops at all.
+    Empty,
+    Unknown(UnknownPaletteError),
+}
+
+impl fmt::Display for LoadPaletteError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            LoadPaletteError::Io(err) => write!(f, "could not read palette: {err}"),
+            LoadPaletteError::Malformed{line, content} => write!(f, "malformed palette line {line}: `{content}`"),
+            LoadPaletteError::Empty => write!(f, "palette has no color stops"),
+            LoadPaletteError::Unknown(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for LoadPaletteError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            LoadPaletteError::Io(err) => Some(err),
+            LoadPaletteError::Malformed{..} | LoadPaletteError::Empty => None,
+            LoadPaletteError::Unknown(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for LoadPaletteError{
+    fn from(err: io::Error) -> Self{
+        LoadPaletteError::Io(err)
+    }
+}
+
+impl From<UnknownPaletteError> for LoadPaletteError{
+    fn from(err: UnknownPaletteError) -> Self{
+        LoadPaletteError::Unknown(err)
+    }
+}
+
+/// Names `Gradient::named` accepts.
+pub const PALETTE_NAMES: [&str; 3] = ["ultra-fractal", "viridis", "twilight"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPaletteError(pub String);
+
+impl fmt::Display for UnknownPaletteError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "unknown palette `{}`, expected one of {}", self.0, PALETTE_NAMES.join(", "))
+    }
+}
+
+impl Error for UnknownPaletteError{}
+
+pub trait Palette{
+    /// Color for a normalized escape value `t` in `[0, 1]`.
+    fn color(&self, t: f32) -> [u8; 3];
+}
+
+/// The same ramp `write_data` produces, bright for fast escapes and dark near the set.
+pub struct Grayscale;
+
+impl Palette for Grayscale{
+    fn color(&self, t: f32) -> [u8; 3]{
+        let value = 255 - (t.clamp(0.0, 1.0) * 255_f32) as u8;
+        [value, value, value]
+    }
+}
+
+/// Piecewise linear gradient between `(position, color)` stops sorted by position.
+#[derive(Clone, Debug)]
+pub struct Gradient{
+    stops: Vec<(f32, [u8; 3])>,
+}
+
+impl Gradient{
+    pub fn new(mut stops: Vec<(f32, [u8; 3])>) -> Self{
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Gradient{stops}
+    }
+
+    /// Blue, white and gold, as in Ultra Fractal's default gradient.
+    pub fn ultra_fractal() -> Self{
+        Gradient::new(vec![
+            (0.0,    [  0,   7, 100]),
+            (0.16,   [ 32, 107, 203]),
+            (0.42,   [237, 255, 255]),
+            (0.6425, [255, 170,   0]),
+            (0.8575, [  0,   2,   0]),
+            (1.0,    [  0,   7, 100]),
+        ])
+    }
+
+    /// Matplotlib's `viridis`, from dark purple to yellow, sampled at every tenth.
+    pub fn viridis() -> Self{
+        Gradient::new(vec![
+            (0.0, [ 68,   1,  84]),
+            (0.1, [ 72,  36, 117]),
+            (0.2, [ 65,  68, 135]),
+            (0.3, [ 53,  95, 141]),
+            (0.4, [ 42, 120, 142]),
+            (0.5, [ 33, 145, 140]),
+            (0.6, [ 34, 168, 132]),
+            (0.7, [ 68, 191, 112]),
+            (0.8, [122, 209,  81]),
+            (0.9, [189, 223,  38]),
+            (1.0, [253, 231,  37]),
+        ])
+    }
+
+    /// Matplotlib's cyclic `twilight` in nine stops, from pale lilac through blue to near black and
+    /// back through red.
+    pub fn twilight() -> Self{
+        Gradient::new(vec![
+            (0.0,   [226, 217, 226]),
+            (0.125, [158, 187, 201]),
+            (0.25,  [103, 133, 190]),
+            (0.375, [ 94,  67, 165]),
+            (0.5,   [ 47,  20,  54]),
+            (0.625, [109,  33,  96]),
+            (0.75,  [176,  83,  76]),
+            (0.875, [207, 158, 140]),
+            (1.0,   [226, 217, 226]),
+        ])
+    }
+
+    /// One of the built-in gradients of `PALETTE_NAMES`.
+    ///
+    /// ```
+    /// use mandelbrot::color::{Gradient, Palette};
+    ///
+    /// let viridis = Gradient::named("viridis").unwrap();
+    /// assert_eq!(viridis.color(0.0), [68, 1, 84]);
+    /// assert_eq!(viridis.color(1.0), [253, 231, 37]);
+    ///
+    /// let err = Gradient::named("jet").unwrap_err();
+    /// assert_eq!(err.to_string(), "unknown palette `jet`, expected one of ultra-fractal, viridis, twilight");
+    /// ```
+    pub fn named(name: &str) -> Result<Self, UnknownPaletteError>{
+        match name {
+            "ultra-fractal" => Ok(Gradient::ultra_fractal()),
+            "viridis" => Ok(Gradient::viridis()),
+            "twilight" => Ok(Gradient::twilight()),
+            _ => Err(UnknownPaletteError(name.to_string())),
+        }
+    }
+}
+
+impl Gradient{
+    /// Reads `position,r,g,b` stops, one per line, with positions in `[0, 1]` and channels in
+    /// `0..=255`. Blank lines and lines starting with `#` are skipped.
+    ///
+    /// ```
+    /// use mandelbrot::color::{Gradient, LoadPaletteError, Palette};
+    ///
+    /// let path = std::env::temp_dir().join("mandelbrot-csv-gradient-doctest.csv");
+    /// std::fs::write(&path, "# black, red, white\n0.0,0,0,0\n0.5,200,0,0\n1.0,255,255,255\n").unwrap();
+    /// let gradient = Gradient::from_csv(&path).unwrap();
+    /// assert_eq!(gradient.color(0.25), [100, 0, 0]);
+    /// assert_eq!(gradient.color(0.5), [200, 0, 0]);
+    /// assert_eq!(gradient.color(0.75), [228, 128, 128]);
+    ///
+    /// std::fs::write(&path, "0.0,0,0,0\n0.5,red\n").unwrap();
+    /// let err = Gradient::from_csv(&path).unwrap_err();
+    /// std::fs::remove_file(&path).unwrap();
+    /// assert!(matches!(err, LoadPaletteError::Malformed{line: 2, ..}));
+    /// ```
+    pub fn from_csv(path: impl AsRef<Path>) -> Result<Self, LoadPaletteError>{
+        let text = fs::read_to_string(path)?;
+        let mut stops = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let malformed = || LoadPaletteError::Malformed{line: number + 1, content: line.to_string()};
+
+            let fields: Vec<&str> = trimmed.split(',').map(str::trim).collect();
+            let [position, r, g, b] = fields[..] else { return Err(malformed()) };
+            let position: f32 = position.parse().map_err(|_| malformed())?;
+            if !(0.0..=1.0).contains(&position) {
+                return Err(malformed());
+            }
+            let channel = |value: &str| value.parse::<u8>().map_err(|_| malformed());
+            stops.push((position, [channel(r)?, channel(g)?, channel(b)?]));
+        }
+        Gradient::from_stops(stops)
+    }
+
+    /// Reads a GIMP gradient. Every segment becomes a stop at each of its ends; midpoints, blending
+    /// functions and alpha are ignored, so curved segments come out linear.
+    ///
+    /// ```
+    /// use mandelbrot::color::{Gradient, Palette};
+    ///
+    /// let path = std::env::temp_dir().join("mandelbrot-ggr-gradient-doctest.ggr");
+    /// std::fs::write(&path, "GIMP Gradient\nName: Three stops\n2\n\
+    ///     0.0 0.25 0.5 0 0 0 1 0 0 1 1 0 0\n\
+    ///     0.5 0.75 1.0 0 0 1 1 1 1 1 1 0 0\n").unwrap();
+    /// let gradient = Gradient::from_ggr(&path).unwrap();
+    /// std::fs::remove_file(&path).unwrap();
+    /// assert_eq!(gradient.color(0.25), [0, 0, 128]);
+    /// assert_eq!(gradient.color(0.5), [0, 0, 255]);
+    /// assert_eq!(gradient.color(0.75), [128, 128, 255]);
+    /// ```
+    pub fn from_ggr(path: impl AsRef<Path>) -> Result<Self, LoadPaletteError>{
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines().enumerate();
+        let mut next_line = || lines.next().map(|(number, line)| (number + 1, line));
+        let malformed = |line: usize, content: &str| LoadPaletteError::Malformed{line, content: content.to_string()};
+
+        match next_line() {
+            Some((_, "GIMP Gradient")) => {}
+            Some((number, line)) => return Err(malformed(number, line)),
+            None => return Err(LoadPaletteError::Empty),
+        }
+        let (mut number, mut line) = next_line().ok_or(LoadPaletteError::Empty)?;
+        if line.starts_with("Name:") {
+            (number, line) = next_line().ok_or(LoadPaletteError::Empty)?;
+        }
+        let segments: usize = line.trim().parse().map_err(|_| malformed(number, line))?;
+
+        let mut stops = Vec::with_capacity(segments * 2);
+        for _ in 0..segments {
+            let (number, line) = next_line().ok_or(LoadPaletteError::Empty)?;
+            let fields: Vec<f32> = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| malformed(number, line))?;
+            if fields.len() < 11 {
+                return Err(malformed(number, line));
+            }
+            let color = |offset: usize| -> [u8; 3] {
+                std::array::from_fn(|channel| (fields[offset + channel].clamp(0.0, 1.0) * 255.0).round() as u8)
+            };
+            stops.push((fields[0], color(3)));
+            stops.push((fields[2], color(7)));
+        }
+        Gradient::from_stops(stops)
+    }
+
+    fn from_stops(stops: Vec<(f32, [u8; 3])>) -> Result<Self, LoadPaletteError>{
+        if stops.is_empty() {
+            return Err(LoadPaletteError::Empty);
+        }
+        Ok(Gradient::new(stops))
+    }
+}
+
+impl Palette for Gradient{
+    fn color(&self, t: f32) -> [u8; 3]{
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return [0, 0, 0],
+        };
+
+        if t <= first.0 {
+            return first.1;
+        }
+
+        for pair in self.stops.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            if t <= end.0 {
+                let span = end.0 - start.0;
+                let weight = if span > 0.0 { (t - start.0) / span } else { 1.0 };
+                return std::array::from_fn(|channel| {
+                    let (a, b) = (start.1[channel] as f32, end.1[channel] as f32);
+                    (a + (b - a) * weight).round() as u8
+                });
+            }
+        }
+
+        last.1
+    }
+}
+
+/// Brightness `level / max` raised to `1 / gamma`, rounded back onto `0..=max`. Gamma 1 is left as is.
+fn gamma_corrected(level: f32, max: f32, gamma: f32) -> f32{
+    if gamma == 1.0 {
+        level
+    } else {
+        (max * (level / max).powf(1.0 / gamma)).round()
+    }
+}
+
+/// `const_mul` is `255 / limit`. Values past the limit are clamped to the darkest level rather than
+/// wrapping around to a bright one.
+pub(crate) fn gray_level<T: Into<f32>>(value: Option<T>, const_mul: f32, gamma: f32) -> u8{
+    match value {
+        None => { 0 }
+        Some(val) => {
+            let darkness = (val.into() * const_mul).clamp(0.0, 255.0) as u8;
+            gamma_corrected((255 - darkness) as f32, 255.0, gamma) as u8
+        }
+    }
+}
+
+/// `gray_level` over the 65536 levels of `L16`, `const_mul` being `65535 / limit`.
+pub(crate) fn gray_level_16<T: Into<f32>>(value: Option<T>, const_mul: f32, gamma: f32) -> u16{
+    match value {
+        None => { 0 }
+        Some(val) => {
+            let darkness = (val.into() * const_mul).clamp(0.0, 65535.0) as u16;
+            gamma_corrected((65535 - darkness) as f32, 65535.0, gamma) as u16
+        }
+    }
+}
+
+/// Gray level of every escape count up to a limit, as `write_data` maps them, so that coloring a
+/// pixel takes one lookup instead of float math.
+///
+/// ```
+/// use mandelbrot::color::{write_data, write_lut_data, GrayLut};
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::Image;
+///
+/// let resolution = Resolution{width: 300, height: 1};
+/// let counts = || (0..300_u16).map(|x| ((x as u32, 0), (x % 7 != 0).then_some(x)));
+/// for (limit, gamma) in [(256, 1.0), (300, 2.2), (1000, 0.5), (39, 1.0)] {
+///     let (mut direct, mut looked_up) = (Image::new(&resolution), Image::new(&resolution));
+///     write_data(&mut direct, counts(), limit, gamma).unwrap();
+///     write_lut_data(&mut looked_up, counts(), &GrayLut::new(limit, gamma)).unwrap();
+///     assert_eq!(direct.data, looked_up.data);
+/// }
+/// ```
+pub struct GrayLut{
+    levels: Box<[u8]>,
+}
+
+impl GrayLut{
+    pub fn new(limit: EscapeLimit, gamma: f32) -> Self{
+        let const_mul = 255_f32 / limit as f32;
+        let levels = (0..=limit).map(|count| gray_level(Some(count), const_mul, gamma)).collect();
+        GrayLut{levels}
+    }
+
+    /// Counts past the limit get the level of the limit, as the saturating float math gives them.
+    pub fn level(&self, escape_time: Option<EscapeLimit>) -> u8{
+        match escape_time {
+            None => 0,
+            Some(count) => self.levels[(count as usize).min(self.levels.len() - 1)],
+        }
+    }
+}
+
+/// Palette colors of every escape count up to a limit, as `write_colored_data` picks them.
+pub struct PaletteLut{
+    colors: Box<[[u8; 3]]>,
+}
+
+impl PaletteLut{
+    pub fn new(limit: EscapeLimit, palette: &impl Palette) -> Self{
+        let colors = (0..=limit).map(|count| palette.color(count as f32 / limit as f32)).collect();
+        PaletteLut{colors}
+    }
+
+    pub fn color(&self, escape_time: Option<EscapeLimit>) -> [u8; 3]{
+        match escape_time {
+            None => [0, 0, 0],
+            Some(count) => self.colors[(count as usize).min(self.colors.len() - 1)],
+        }
+    }
+}
+
+/// Curve escape values follow on their way to brightness. Escape counts crowd at the low end, so
+/// `Log` and `Sqrt` spread them over more of the gray levels than `Linear` does.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ColorScale{
+    #[default]
+    Linear,
+    /// `ln(1 + value) / ln(1 + limit)`.
+    Log,
+    /// `sqrt(value / limit)`.
+    Sqrt,
+}
+
+impl ColorScale{
+    /// `value` moved along the curve, staying within `0..=limit`.
+    pub fn rescale(&self, value: f32, limit: f32) -> f32{
+        match self {
+            ColorScale::Linear => value,
+            ColorScale::Log => value.max(0.0).ln_1p() / limit.ln_1p() * limit,
+            ColorScale::Sqrt => (value.max(0.0) / limit).sqrt() * limit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorScaleError(pub String);
+
+impl fmt::Display for ParseColorScaleError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "unknown color scale `{}`", self.0)
+    }
+}
+
+impl Error for ParseColorScaleError{}
+
+/// Accepts `linear`, `log` and `sqrt`.
+impl std::str::FromStr for ColorScale{
+    type Err = ParseColorScaleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>{
+        match s {
+            "linear" => Ok(ColorScale::Linear),
+            "log" => Ok(ColorScale::Log),
+            "sqrt" => Ok(ColorScale::Sqrt),
+            _ => Err(ParseColorScaleError(s.to_string())),
+        }
+    }
+}
+
+/// Noise added to the gray levels before they are rounded to 8 bits, breaking the bands smooth
+/// gradients otherwise show into a fine pattern.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Dither{
+    #[default]
+    Off,
+    /// An 8 by 8 Bayer matrix, tiled over the image from an origin the seed picks.
+    Ordered,
+    /// White noise, the same for the same seed and pixel whatever order pixels are written in.
+    Noise,
+}
+
+impl Dither{
+    /// Offset in `[-0.5, 0.5)` to add to the level of pixel `(x, y)` before rounding.
+    pub fn offset(&self, x: u32, y: u32, seed: u64) -> f32{
+        match self {
+            Dither::Off => 0.0,
+            Dither::Ordered => {
+                let (x, y) = (x.wrapping_add(seed as u32) & 7, y.wrapping_add((seed >> 32) as u32) & 7);
+                // Interleaving the bits of x ^ y and y, reversed, gives the Bayer index.
+                let (a, b) = (x ^ y, y);
+                let index = ((a & 1) << 5) | ((b & 1) << 4) | ((a & 2) << 2) | ((b & 2) << 1) | ((a & 4) >> 1) | ((b & 4) >> 2);
+                index as f32 / 64.0 - 0.5
+            }
+            Dither::Noise => {
+                // splitmix64 of the seed and position.
+                let mut z = seed ^ ((x as u64) << 32 | y as u64);
+                z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^= z >> 31;
+                (z >> 40) as f32 / (1_u64 << 24) as f32 - 0.5
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDitherError(pub String);
+
+impl fmt::Display for ParseDitherError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "unknown dither `{}`", self.0)
+    }
+}
+
+impl Error for ParseDitherError{}
+
+/// Accepts `off`, `ordered` and `noise`.
+impl std::str::FromStr for Dither{
+    type Err = ParseDitherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>{
+        match s {
+            "off" => Ok(Dither::Off),
+            "ordered" => Ok(Dither::Ordered),
+            "noise" => Ok(Dither::Noise),
+            _ => Err(ParseDitherError(s.to_string())),
+        }
+    }
+}
+
+/// Maps escape values onto grayscale. Accepts both integer escape counts and smooth (fractional) ones.
+/// `gamma` above 1 brightens the mid-tones for display, 1 keeps the ramp linear. Returns how many
+/// pixels were written.
+///
+/// Into an `La8` image, pixels the data leaves out stay transparent:
+///
+/// ```
+/// use mandelbrot::color::write_data;
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{encode_png, Image, PixelFormat};
+///
+/// let mut image = Image::with_format(&Resolution{width: 2, height: 1}, PixelFormat::La8);
+/// write_data(&mut image, [((0, 0), None::<u16>)].into_iter(), 256, 1.0).unwrap();
+/// let decoded = image::load_from_memory(&encode_png(&image, None).unwrap()).unwrap().into_luma_alpha8();
+/// assert_eq!(decoded.get_pixel(0, 0).0, [0, 255]);
+/// assert_eq!(decoded.get_pixel(1, 0).0[1], 0);
+/// ```
+///
+/// Escaping at iteration 0 is white, the opposite end of the ramp from the interior:
+///
+/// ```
+/// use mandelbrot::color::write_data;
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::Image;
+///
+/// let mut image = Image::new(&Resolution{width: 2, height: 1});
+/// write_data(&mut image, [((0, 0), Some(0_u16)), ((1, 0), None)].into_iter(), 256, 1.0).unwrap();
+/// assert_eq!(&image.data[..], [255, 0]);
+/// ```
+///
+/// Values past the limit stay at the dark end instead of wrapping around to a bright speckle:
+///
+/// ```
+/// use mandelbrot::color::write_data;
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::Image;
+///
+/// let mut image = Image::new(&Resolution{width: 3, height: 1});
+/// let data = [((0, 0), Some(255.0_f32)), ((1, 0), Some(256.0)), ((2, 0), Some(1000.0))];
+/// write_data(&mut image, data.into_iter(), 256, 1.0).unwrap();
+/// assert_eq!(&image.data[..], [1, 0, 0]);
+/// ```
+///
+/// Gamma 2.2 brightens the mid-tones and leaves both ends of the ramp in place:
+///
+/// ```
+/// use mandelbrot::color::write_data;
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::Image;
+///
+/// let levels = |gamma| {
+///     let mut image = Image::new(&Resolution{width: 4, height: 1});
+///     let data = [((0, 0), Some(0.0_f32)), ((1, 0), Some(64.0)), ((2, 0), Some(128.0)), ((3, 0), Some(256.0))];
+///     write_data(&mut image, data.into_iter(), 256, gamma).unwrap();
+///     image.data.to_vec()
+/// };
+/// let (linear, corrected) = (levels(1.0), levels(2.2));
+/// assert_eq!(linear, [255, 192, 128, 0]);
+/// assert_eq!((corrected[0], corrected[3]), (255, 0));
+/// assert!(corrected[1] > linear[1] && corrected[2] > linear[2]);
+/// // 255 * 0.5^(1 / 2.2)
+/// assert!(corrected[2].abs_diff(186) <= 1);
+/// ```
+///
+/// Calculations made for a larger resolution fail on the first pixel past the edge:
+///
+/// ```
+/// use mandelbrot::color::{write_data, WriteError};
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::image::Image;
+///
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.0, 1.0).unwrap();
+/// let data = make_calculations(Resolution{width: 8, height: 8}, rect, 64, Precision::Single, EscapeParams::default()).unwrap();
+/// let mut image = Image::new(&Resolution{width: 4, height: 4});
+/// let result = write_data(&mut image, data, 64, 1.0);
+/// assert_eq!(result, Err(WriteError::OutOfBounds{position: (4, 0), width: 4, height: 4}));
+/// ```
+pub fn write_data<T: Into<f32>>(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), Option<T>)>,
+        escape_limit: EscapeLimit,
+        gamma: f32,
+    ) -> Result<usize, WriteError> {
+    write_scaled_data(img, data, escape_limit, ColorScale::Linear, gamma)
+}
+
+/// `write_data` with the escape values passed through `scale` first. Writes `L8` or `La8` images,
+/// the latter opaque wherever a value was written.
+///
+/// ```
+/// use mandelbrot::color::{write_scaled_data, ColorScale};
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::Image;
+///
+/// let brightness = |scale| {
+///     let mut image = Image::new(&Resolution{width: 1, height: 1});
+///     write_scaled_data(&mut image, [((0, 0), Some(4_u16))].into_iter(), 256, scale, 1.0).unwrap();
+///     image.data[0]
+/// };
+/// // A low count stays near white on the linear scale, the log scale moves it well into the grays.
+/// assert!(brightness(ColorScale::Log) < brightness(ColorScale::Linear));
+/// ```
+pub fn write_scaled_data<T: Into<f32>>(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), Option<T>)>,
+        escape_limit: EscapeLimit,
+        scale: ColorScale,
+        gamma: f32,
+    ) -> Result<usize, WriteError> {
+    write_dithered_data(img, data, escape_limit, scale, gamma, Dither::Off, 0)
+}
+
+/// `write_scaled_data` with `dither` applied before rounding, seeded by `seed`. `Dither::Off` writes
+/// the same levels as `write_scaled_data`. Writes `L8` or `La8` images, so the 16-bit `L16` goes
+/// through `write_l16_data` instead.
+///
+/// ```
+/// use mandelbrot::color::{write_dithered_data, ColorScale, Dither, WriteError};
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{Image, PixelFormat};
+///
+/// // A ramp across three gray levels, which rounding alone turns into three flat bands.
+/// let resolution = Resolution{width: 64, height: 8};
+/// let ramp = (0..8).flat_map(|y| (0..64).map(move |x| ((x, y), Some(100.0 + 3.0 * x as f32 / 64.0))));
+/// let changes = |dither| {
+///     let mut image = Image::new(&resolution);
+///     write_dithered_data(&mut image, ramp.clone(), 255, ColorScale::Linear, 1.0, dither, 7).unwrap();
+///     image.data.windows(2).filter(|pair| pair[0] != pair[1]).count()
+/// };
+/// assert!(changes(Dither::Ordered) > 4 * changes(Dither::Off));
+/// assert!(changes(Dither::Noise) > 4 * changes(Dither::Off));
+///
+/// let mut wide = Image::with_format(&resolution, PixelFormat::L16);
+/// let written = write_dithered_data(&mut wide, ramp, 255, ColorScale::Linear, 1.0, Dither::Off, 7);
+/// assert_eq!(written, Err(WriteError::UnsupportedFormat(PixelFormat::L16)));
+/// assert!(wide.data.iter().all(|&byte| byte == 0));
+/// ```
+pub fn write_dithered_data<T: Into<f32>>(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), Option<T>)>,
+        escape_limit: EscapeLimit,
+        scale: ColorScale,
+        gamma: f32,
+        dither: Dither,
+        seed: u64,
+    ) -> Result<usize, WriteError> {
+
+    check_gray(img)?;
+    let const_mul =  255_f32 / escape_limit as f32;
+    let limit = escape_limit as f32;
+    let mut written = 0;
+    for (position, value) in data {
+        let index = pixel_index(&img.resolution, position)?;
+
+        let value = value.map(|value| scale.rescale(value.into(), limit));
+        let level = match (dither, value) {
+            (Dither::Off, _) | (_, None) => gray_level(value, const_mul, gamma),
+            (_, Some(value)) => {
+                let level = 255.0 - value * const_mul;
+                let level = if gamma == 1.0 { level } else { 255.0 * (level / 255.0).max(0.0).powf(1.0 / gamma) };
+                (level + 0.5 + dither.offset(position.0, position.1, seed)).floor().clamp(0.0, 255.0) as u8
+            }
+        };
+        put_gray(img, index, level);
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// How `write_interior_data` shades the pixels inside the set.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InteriorColoring{
+    /// A single gray level, black for `Solid(0)`.
+    Solid(u8),
+    /// Brighter where the orbit ended further from the origin.
+    Magnitude,
+    /// Brighter where the orbit's average lies further from the origin.
+    MeanPosition,
+}
+
+impl Default for InteriorColoring{
+    fn default() -> Self{
+        InteriorColoring::Solid(0)
+    }
+}
+
+impl InteriorColoring{
+    fn gray_level(&self, interior: &InteriorData) -> u8{
+        // Orbits of interior points stay within |z| <= 2.
+        let level = |magnitude: f32| ((magnitude / 2.0).clamp(0.0, 1.0) * 255_f32) as u8;
+        match self {
+            InteriorColoring::Solid(level) => *level,
+            InteriorColoring::Magnitude => level(interior.final_magnitude),
+            InteriorColoring::MeanPosition => level(interior.mean_position.norm()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseInteriorColoringError(pub String);
+
+impl fmt::Display for ParseInteriorColoringError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "unknown interior coloring `{}`", self.0)
+    }
+}
+
+impl Error for ParseInteriorColoringError{}
+
+/// Accepts `magnitude`, `mean`, `black` or a gray level from 0 to 255.
+impl std::str::FromStr for InteriorColoring{
+    type Err = ParseInteriorColoringError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>{
+        match s {
+            "magnitude" => Ok(InteriorColoring::Magnitude),
+            "mean" => Ok(InteriorColoring::MeanPosition),
+            "black" => Ok(InteriorColoring::Solid(0)),
+            _ => s.parse().map(InteriorColoring::Solid).map_err(|_| ParseInteriorColoringError(s.to_string())),
+        }
+    }
+}
+
+/// `write_data` for `make_escape_outcomes`, shading the interior through `interior` instead of
+/// leaving it black. Writes `L8` or `La8` images. Returns how many pixels were written.
+///
+/// ```
+/// use mandelbrot::color::{write_interior_data, InteriorColoring, WriteError};
+/// use mandelbrot::compute::{make_escape_outcomes, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::image::{Image, PixelFormat};
+///
+/// // Entirely inside the main cardioid, whose orbits end at various distances from the origin.
+/// let resolution = Resolution{width: 16, height: 16};
+/// let rect = Rect::from_bounds(-0.5, 0.1, -0.3, 0.3).unwrap();
+/// let outcomes = || make_escape_outcomes(resolution, rect, 200, Precision::Single, EscapeParams::default()).unwrap();
+///
+/// let mut black = Image::new(&resolution);
+/// write_interior_data(&mut black, outcomes(), 200, 1.0, InteriorColoring::default()).unwrap();
+/// assert!(black.data.iter().all(|&level| level == 0));
+///
+/// let mut magnitude = Image::new(&resolution);
+/// write_interior_data(&mut magnitude, outcomes(), 200, 1.0, InteriorColoring::Magnitude).unwrap();
+/// let (min, max) = (magnitude.data.iter().min().unwrap(), magnitude.data.iter().max().unwrap());
+/// assert!(min < max && *max > 0);
+///
+/// let mut rgb = Image::with_format(&resolution, PixelFormat::Rgb8);
+/// let written = write_interior_data(&mut rgb, outcomes(), 200, 1.0, InteriorColoring::Magnitude);
+/// assert_eq!(written, Err(WriteError::UnsupportedFormat(PixelFormat::Rgb8)));
+/// ```
+pub fn write_interior_data(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), EscapeOutcome)>,
+        escape_limit: EscapeLimit,
+        gamma: f32,
+        interior: InteriorColoring,
+    ) -> Result<usize, WriteError> {
+
+    check_gray(img)?;
+    let const_mul =  255_f32 / escape_limit as f32;
+    let mut written = 0;
+    for (position, outcome) in data {
+        let index = pixel_index(&img.resolution, position)?;
+
+        let level = match outcome {
+            EscapeOutcome::Escaped(escape_time) => gray_level(Some(escape_time), const_mul, gamma),
+            EscapeOutcome::Inside(data) => interior.gray_level(&data),
+        };
+        put_gray(img, index, level);
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// `write_data` into an `L16` image, which keeps up to 65536 distinct escape counts apart instead of 256.
+/// Returns how many pixels were written.
+///
+/// ```
+/// use std::collections::HashSet;
+/// use mandelbrot::color::{write_data, write_l16_data};
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::image::{Image, PixelFormat};
+///
+/// let resolution = Resolution{width: 128, height: 128};
+/// let rect = Rect::from_bounds(-0.75, -0.73, 0.1, 0.12).unwrap();
+/// let calculations = || make_calculations(resolution, rect, 2000, Precision::Single, EscapeParams::default()).unwrap();
+/// let mut narrow = Image::new(&resolution);
+/// let mut wide = Image::with_format(&resolution, PixelFormat::L16);
+/// write_data(&mut narrow, calculations(), 2000, 1.0).unwrap();
+/// write_l16_data(&mut wide, calculations(), 2000, 1.0).unwrap();
+///
+/// let narrow_levels: HashSet<_> = narrow.data.iter().collect();
+/// let wide_levels: HashSet<_> = wide.data.chunks(2).collect();
+/// assert!(wide_levels.len() > narrow_levels.len() * 2, "{} vs {}", wide_levels.len(), narrow_levels.len());
+/// ```
+pub fn write_l16_data<T: Into<f32>>(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), Option<T>)>,
+        escape_limit: EscapeLimit,
+        gamma: f32,
+    ) -> Result<usize, WriteError> {
+    debug_assert_eq!(img.format, PixelFormat::L16);
+
+    let const_mul =  65535_f32 / escape_limit as f32;
+    let mut written = 0;
+    for (position, value) in data {
+        let index = pixel_index(&img.resolution, position)? * 2;
+
+        img.data[index..index + 2].copy_from_slice(&gray_level_16(value, const_mul, gamma).to_be_bytes());
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// Colors escape values through `palette` into an `Rgb8` or `Rgba8` image. Points inside the set are
+/// black. Returns how many pixels were written.
+///
+/// ```
+/// use mandelbrot::color::{write_colored_data, Gradient, WriteError};
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{Image, PixelFormat};
+///
+/// let resolution = Resolution{width: 3, height: 1};
+/// let data = || [((0, 0), Some(0_u16)), ((1, 0), Some(128)), ((2, 0), None)].into_iter();
+/// let gradient = Gradient::new(vec![(0.0, [0, 0, 255]), (1.0, [255, 0, 0])]);
+/// let mut rgba = Image::with_format(&resolution, PixelFormat::Rgba8);
+/// assert_eq!(write_colored_data(&mut rgba, data(), 256, &gradient), Ok(3));
+/// assert_eq!(rgba.data[..], [0, 0, 255, 255, 128, 0, 128, 255, 0, 0, 0, 255]);
+///
+/// let mut gray = Image::new(&resolution);
+/// assert_eq!(write_colored_data(&mut gray, data(), 256, &gradient), Err(WriteError::UnsupportedFormat(PixelFormat::L8)));
+/// ```
+pub fn write_colored_data<T: Into<f32>>(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), Option<T>)>,
+        escape_limit: EscapeLimit,
+        palette: &impl Palette,
+    ) -> Result<usize, WriteError> {
+    check_rgb(img)?;
+
+    let mut written = 0;
+    for (position, value) in data {
+        let index = pixel_index(&img.resolution, position)?;
+
+        let color = match value {
+            None => { [0, 0, 0] }
+            Some(val) => { palette.color(val.into() / escape_limit as f32) }
+        };
+        put_rgb(img, index, color);
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// `write_data` for integer escape counts through a precomputed `GrayLut`, into an `L8` or `La8` image.
+/// Returns how many pixels were written.
+///
+/// ```
+/// use mandelbrot::color::{write_lut_data, GrayLut, WriteError};
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{Image, PixelFormat};
+///
+/// let lut = GrayLut::new(256, 1.0);
+/// let data = || [((0, 0), Some(64_u16)), ((1, 0), None)].into_iter();
+/// let mut transparent = Image::with_format(&Resolution{width: 2, height: 1}, PixelFormat::La8);
+/// assert_eq!(write_lut_data(&mut transparent, data(), &lut), Ok(2));
+/// assert_eq!(transparent.data[..], [lut.level(Some(64)), 255, 0, 255]);
+///
+/// let mut rgb = Image::with_format(&Resolution{width: 2, height: 1}, PixelFormat::Rgb8);
+/// assert_eq!(write_lut_data(&mut rgb, data(), &lut), Err(WriteError::UnsupportedFormat(PixelFormat::Rgb8)));
+/// ```
+pub fn write_lut_data(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), Option<EscapeLimit>)>,
+        lut: &GrayLut,
+    ) -> Result<usize, WriteError> {
+    check_gray(img)?;
+    let mut written = 0;
+    for (position, escape_time) in data {
+        let index = pixel_index(&img.resolution, position)?;
+        put_gray(img, index, lut.level(escape_time));
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// `write_colored_data` for integer escape counts through a precomputed `PaletteLut`, into an `Rgb8`
+/// or `Rgba8` image. Returns how many pixels were written.
+///
+/// ```
+/// use mandelbrot::color::{write_colored_data, write_colored_lut_data, Gradient, PaletteLut};
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{Image, PixelFormat};
+///
+/// let resolution = Resolution{width: 256, height: 1};
+/// let counts = || (0..256_u16).map(|x| ((x as u32, 0), (x % 5 != 0).then_some(x)));
+/// let gradient = Gradient::ultra_fractal();
+/// let mut direct = Image::with_format(&resolution, PixelFormat::Rgb8);
+/// let mut looked_up = Image::with_format(&resolution, PixelFormat::Rgb8);
+/// write_colored_data(&mut direct, counts(), 256, &gradient).unwrap();
+/// write_colored_lut_data(&mut looked_up, counts(), &PaletteLut::new(256, &gradient)).unwrap();
+/// assert_eq!(direct.data, looked_up.data);
+/// ```
+pub fn write_colored_lut_data(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), Option<EscapeLimit>)>,
+        lut: &PaletteLut,
+    ) -> Result<usize, WriteError> {
+    check_rgb(img)?;
+
+    let mut written = 0;
+    for (position, escape_time) in data {
+        let index = pixel_index(&img.resolution, position)?;
+        put_rgb(img, index, lut.color(escape_time));
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// Colors `make_escape_outcomes` with two palettes into an `Rgb8` or `Rgba8` image: escaped points
+/// through `exterior` by escape value, points inside the set through `interior` at the level
+/// `shading` gives them. Returns how many pixels were written.
+///
+/// ```
+/// use mandelbrot::color::{write_split_data, Gradient, Grayscale, InteriorColoring};
+/// use mandelbrot::compute::{EscapeOutcome, InteriorData};
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{Image, PixelFormat};
+/// use num::Complex;
+///
+/// let mut image = Image::with_format(&Resolution{width: 2, height: 1}, PixelFormat::Rgb8);
+/// let inside = InteriorData{final_magnitude: 0.5, mean_position: Complex::new(0.0, 0.0)};
+/// // Both land halfway along their palette.
+/// let data = [((0, 0), EscapeOutcome::Escaped(128)), ((1, 0), EscapeOutcome::Inside(inside))];
+/// let cool = Gradient::new(vec![(0.0, [0, 0, 64]), (1.0, [64, 128, 255])]);
+/// write_split_data(&mut image, data.into_iter(), 255, &Grayscale, &cool, InteriorColoring::Solid(128)).unwrap();
+/// assert_ne!(image.data[0..3], image.data[3..6]);
+/// ```
+pub fn write_split_data(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), EscapeOutcome)>,
+        escape_limit: EscapeLimit,
+        exterior: &impl Palette,
+        interior: &impl Palette,
+        shading: InteriorColoring,
+    ) -> Result<usize, WriteError> {
+    check_rgb(img)?;
+
+    let mut written = 0;
+    for (position, outcome) in data {
+        let index = pixel_index(&img.resolution, position)?;
+
+        let color = match outcome {
+            EscapeOutcome::Escaped(escape_time) => exterior.color(escape_time as f32 / escape_limit as f32),
+            EscapeOutcome::Inside(data) => interior.color(shading.gray_level(&data) as f32 / 255_f32),
+        };
+        put_rgb(img, index, color);
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// Repeats the palette every `period` escape values instead of stretching it once over the limit,
+/// giving the banded look of classic renders. `offset` shifts the bands, e.g. to animate them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PaletteCycle{
+    pub period: f32,
+    pub offset: f32,
+}
+
+impl PaletteCycle{
+    /// Palette position of `value`, `((value + offset) mod period) / period`.
+    pub fn position(&self, value: f32) -> f32{
+        (value + self.offset).rem_euclid(self.period) / self.period
+    }
+}
+
+/// `write_colored_data` with the palette cycling as `cycle` says. Points inside the set are black.
+/// Returns how many pixels were written.
+///
+/// ```
+/// use mandelbrot::color::{write_cycled_data, Gradient, PaletteCycle};
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{Image, PixelFormat};
+///
+/// let mut image = Image::with_format(&Resolution{width: 3, height: 1}, PixelFormat::Rgb8);
+/// let data = [((0, 0), Some(5_u16)), ((1, 0), Some(21)), ((2, 0), Some(13))];
+/// let cycle = PaletteCycle{period: 16.0, offset: 3.0};
+/// write_cycled_data(&mut image, data.into_iter(), &Gradient::ultra_fractal(), cycle).unwrap();
+/// assert_eq!(image.data[0..3], image.data[3..6]);
+/// assert_ne!(image.data[0..3], image.data[6..9]);
+/// ```
+pub fn write_cycled_data<T: Into<f32>>(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), Option<T>)>,
+        palette: &impl Palette,
+        cycle: PaletteCycle,
+    ) -> Result<usize, WriteError> {
+    check_rgb(img)?;
+
+    let mut written = 0;
+    for (position, value) in data {
+        let index = pixel_index(&img.resolution, position)?;
+
+        let color = match value {
+            None => { [0, 0, 0] }
+            Some(val) => { palette.color(cycle.position(val.into())) }
+        };
+        put_rgb(img, index, color);
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// Grayscale of orbit trap distances: orbits touching the trap are white, fading to black at
+/// `max_distance` and beyond. Writes `L8` or `La8` images. Returns how many pixels were written.
+pub fn write_trap_data(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), f32)>,
+        max_distance: f32,
+        gamma: f32,
+    ) -> Result<usize, WriteError> {
+
+    check_gray(img)?;
+    let const_mul = 255_f32 / max_distance;
+    let mut written = 0;
+    for (position, distance) in data {
+        let index = pixel_index(&img.resolution, position)?;
+
+        put_gray(img, index, gray_level(Some(distance.min(max_distance)), const_mul, gamma));
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// Grayscale of stripe averages, which run from 0 for black to 1 for white. Inside the set is black.
+/// Writes `L8` or `La8` images. Returns how many pixels were written.
+pub fn write_stripe_data(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), Option<f32>)>,
+        gamma: f32,
+    ) -> Result<usize, WriteError> {
+
+    check_gray(img)?;
+    let mut written = 0;
+    for (position, stripes) in data {
+        let index = pixel_index(&img.resolution, position)?;
+
+        let level = match stripes {
+            None => { 0 }
+            Some(stripes) => gamma_corrected(stripes.clamp(0.0, 1.0) * 255_f32, 255.0, gamma) as u8,
+        };
+        put_gray(img, index, level);
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// How many doublings of the pixel size `write_distance_data` spreads its ramp over.
+const DISTANCE_OCTAVES: f32 = 8.0;
+
+/// Logarithmic grayscale of boundary distances: pixels within `pixel_size` of the set are black and
+/// the ramp brightens with every doubling of the distance, which draws the boundary as a thin line at
+/// any zoom. Writes `L8` or `La8` images. Returns how many pixels were written.
+pub fn write_distance_data(
+        img: &mut Image,
+        data: impl Iterator<Item=((u32, u32), Option<f32>)>,
+        pixel_size: f32,
+    ) -> Result<usize, WriteError> {
+
+    check_gray(img)?;
+    let mut written = 0;
+    for (position, distance) in data {
+        let index = pixel_index(&img.resolution, position)?;
+
+        let level = match distance {
+            None => { 0 }
+            Some(distance) => {
+                let t = ((distance / pixel_size).log2() / DISTANCE_OCTAVES).clamp(0.0, 1.0);
+                (t * 255_f32) as u8
+            }
+        };
+        put_gray(img, index, level);
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// Direction the light of `write_shaded_data` shines from, in degrees: `azimuth` counterclockwise from
+/// the right edge of the image, `elevation` up from the image plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light{
+    pub azimuth:   f32,
+    pub elevation: f32,
+}
+
+impl Default for Light{
+    /// From the top left, halfway up.
+    fn default() -> Self{
+        Light{azimuth: 135.0, elevation: 45.0}
+    }
+}
+
+impl Light{
+    /// Unit vector towards the light, `z` pointing out of the image.
+    fn direction(&self) -> [f32; 3]{
+        let (azimuth, elevation) = (self.azimuth.to_radians(), self.elevation.to_radians());
+        [elevation.cos() * azimuth.cos(), elevation.cos() * azimuth.sin(), elevation.sin()]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLightError(pub String);
+
+impl fmt::Display for ParseLightError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "expected `AZIMUTH,ELEVATION` in degrees, got `{}`", self.0)
+    }
+}
+
+impl Error for ParseLightError{}
+
+/// Accepts `AZIMUTH,ELEVATION` in degrees, such as `135,45`.
+impl std::str::FromStr for Light{
+    type Err = ParseLightError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>{
+        let err = || ParseLightError(s.to_string());
+        let (azimuth, elevation) = s.split_once(',').ok_or_else(err)?;
+        let azimuth: f32 = azimuth.trim().parse().map_err(|_| err())?;
+        let elevation: f32 = elevation.trim().parse().map_err(|_| err())?;
+        if !azimuth.is_finite() || !(0.0..=90.0).contains(&elevation) {
+            return Err(err());
+        }
+        Ok(Light{azimuth, elevation})
+    }
+}
+
+/// Share of the light every lit pixel gets whatever its slope.
+const SHADE_AMBIENT: f32 = 0.15;
+/// Weight of the Lambert term.
+const SHADE_DIFFUSE: f32 = 0.7;
+/// Weight and exponent of the Blinn-Phong highlight.
+const SHADE_SPECULAR: f32 = 0.3;
+const SHADE_SHININESS: f32 = 16.0;
+
+/// Fake relief lighting of boundary distances: the distance ramp of `write_distance_data` is taken for
+/// a height map, its normals found from the neighbouring pixels and lit by `light` with a Lambert and a
+/// Blinn-Phong term, viewed from straight above. Inside the set is black and counts as height 0 for its
+/// neighbours. Writes `L8` or `La8` images. Returns how many pixels were written.
+///
+/// Flat stretches shade uniformly and slopes facing the light come out brighter:
+///
+/// ```
+/// use mandelbrot::color::{write_shaded_data, Light};
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::grid::EscapeGrid;
+/// use mandelbrot::image::Image;
+///
+/// let resolution = Resolution{width: 8, height: 4};
+/// let shade = |distance: fn(u32) -> f32| {
+///     let grid = EscapeGrid::collect(&resolution, (0..32).map(|i| ((i % 8, i / 8), Some(distance(i % 8))))).unwrap();
+///     let mut image = Image::new(&resolution);
+///     write_shaded_data(&mut image, &grid, 1.0, Light{azimuth: 180.0, elevation: 45.0}).unwrap();
+///     image.data
+/// };
+///
+/// let flat = shade(|_| 16.0);
+/// assert!(flat.iter().all(|&level| level == flat[0]));
+///
+/// // A step up between columns 3 and 4 faces the light on the left.
+/// let edge = shade(|x| if x < 4 { 4.0 } else { 64.0 });
+/// assert_eq!(&edge[..3], &flat[..3]);
+/// assert!(edge[3] > flat[3] && edge[4] > flat[4]);
+/// assert_eq!(&edge[5..8], &flat[5..8]);
+/// ```
+pub fn write_shaded_data(img: &mut Image, grid: &EscapeGrid<f32>, pixel_size: f32, light: Light) -> Result<usize, WriteError>{
+    let Resolution{width, height} = grid.resolution;
+    let height_at = |x: u32, y: u32| {
+        let (x, y) = (x.min(width.saturating_sub(1)), y.min(height.saturating_sub(1)));
+        grid.get(x, y).map_or(0.0, |distance| (distance / pixel_size).log2().clamp(0.0, DISTANCE_OCTAVES))
+    };
+    let light = light.direction();
+    let half = normalized([light[0], light[1], light[2] + 1.0]);
+
+    check_gray(img)?;
+    let mut written = 0;
+    for ((x, y), distance) in grid.iter() {
+        let index = pixel_index(&img.resolution, (x, y))?;
+        let level = match distance {
+            None => 0,
+            Some(_) => {
+                // Central differences, clamped at the image edges, with y pointing up.
+                let slope_x = (height_at(x + 1, y) - height_at(x.saturating_sub(1), y)) / 2.0;
+                let slope_y = (height_at(x, y.saturating_sub(1)) - height_at(x, y + 1)) / 2.0;
+                let normal = normalized([-slope_x, -slope_y, 1.0]);
+                let diffuse = dot(normal, light).max(0.0);
+                let specular = dot(normal, half).max(0.0).powf(SHADE_SHININESS);
+                let level = SHADE_AMBIENT + SHADE_DIFFUSE * diffuse + SHADE_SPECULAR * specular;
+                (level.clamp(0.0, 1.0) * 255_f32).round() as u8
+            }
+        };
+        put_gray(img, index, level);
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32{
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalized(v: [f32; 3]) -> [f32; 3]{
+    let length = dot(v, v).sqrt();
+    [v[0] / length, v[1] / length, v[2] / length]
+}
+
+/// Grayscale stretched over the escape values the grid actually holds: the fastest escape becomes white
+/// and the slowest black, instead of scaling by the iteration limit. Writes either `L8` or `L16` images.
+/// Returns how many pixels were written.
+pub fn write_normalized_data<T: Into<f32> + Copy>(img: &mut Image, grid: &EscapeGrid<T>) -> Result<usize, WriteError>{
+    debug_assert!(matches!(img.format, PixelFormat::L8 | PixelFormat::L16));
+
+    let (min, max) = grid.data
+        .iter()
+        .flatten()
+        .map(|&value| value.into())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)));
+
+    let white = match img.format {
+        PixelFormat::L16 => 65535_f32,
+        _ => 255_f32,
+    };
+    let lerp = make_lerp((min, max), (white, 0.0));
+    let level = |value: f32| if max > min { lerp(value) } else { white };
+
+    let mut written = 0;
+    for (position, value) in grid.iter() {
+        let index = pixel_index(&img.resolution, position)?;
+        let level = value.map_or(0.0, |value| level(value.into()));
+        match img.format {
+            PixelFormat::L16 => img.data[index * 2..index * 2 + 2].copy_from_slice(&(level as u16).to_be_bytes()),
+            _ => img.data[index] = level as u8,
+        }
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// Histogram equalized grayscale: each escape count gets a brightness proportional to the share of
+/// escaped pixels that escape faster than it does, spreading the clustered counts over the full range.
+/// Writes `L8` or `La8` images. Returns how many pixels were written.
+///
+/// The escaped pixels pile up in the brightest levels of the linear ramp, and spread out here:
+///
+/// ```
+/// use mandelbrot::color::{write_data, write_equalized_data};
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::grid::EscapeGrid;
+/// use mandelbrot::image::Image;
+///
+/// let resolution = Resolution{width: 96, height: 64};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.0, 1.0).unwrap();
+/// let data = make_calculations(resolution, rect, 256, Precision::Single, EscapeParams::default()).unwrap();
+/// let grid = EscapeGrid::collect(&resolution, data).unwrap();
+///
+/// let (mut linear, mut equalized) = (Image::new(&resolution), Image::new(&resolution));
+/// write_data(&mut linear, grid.iter(), 256, 1.0).unwrap();
+/// write_equalized_data(&mut equalized, &grid).unwrap();
+///
+/// // Largest share of the escaped pixels falling into one of eight equal ranges of gray levels.
+/// let fullest = |image: &Image| {
+///     let mut bins = [0; 8];
+///     for (level, value) in image.data.iter().zip(grid.data.iter()) {
+///         if value.is_some() { bins[*level as usize / 32] += 1 }
+///     }
+///     *bins.iter().max().unwrap() as f32 / bins.iter().sum::<i32>() as f32
+/// };
+/// assert!(fullest(&equalized) < fullest(&linear) / 2.0, "{} vs {}", fullest(&equalized), fullest(&linear));
+/// ```
+pub fn write_equalized_data(img: &mut Image, grid: &EscapeGrid) -> Result<usize, WriteError>{
+    check_gray(img)?;
+    let max = grid.data.iter().flatten().max().copied().unwrap_or(0) as usize;
+    let mut histogram = vec![0_u64; max + 1];
+    for &escape_time in grid.data.iter().flatten() {
+        histogram[escape_time as usize] += 1;
+    }
+
+    let escaped: u64 = histogram.iter().sum();
+    let mut faster = 0;
+    let levels: Vec<u8> = histogram
+        .iter()
+        .map(|&count| {
+            let rank = faster as f32 / escaped.max(1) as f32;
+            faster += count;
+            255 - (rank * 255_f32) as u8
+        })
+        .collect();
+
+    let mut written = 0;
+    for (position, value) in grid.iter() {
+        let index = pixel_index(&img.resolution, position)?;
+        put_gray(img, index, value.map_or(0, |escape_time| levels[escape_time as usize]));
+        written += 1;
+    }
+
+    Ok(written)
+}
@@ -0,0 +1,1309 @@
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use num::{pow, Complex, Float};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::geometry::{make_lerp, Range, Rect, RectError, Resolution};
+
+pub type EscapeLimit = u16;
+
+/// Limits below this stop iterating long before most of the boundary shows. A limit of 0 never
+/// iterates at all, so every point that does not start outside the bailout radius counts as inside.
+pub const SMALL_LIMIT: EscapeLimit = 16;
+
+/// Number type the escape time functions iterate in, with the conversions they need for their f32
+/// and f64 parameters. Implemented for `f32` and `f64`.
+pub trait FractalFloat: Float{
+    fn from_f32(value: f32) -> Self;
+    fn from_f64(value: f64) -> Self;
+    /// Rounds to the nearest f32, overflowing to infinity.
+    fn to_f32_lossy(self) -> f32;
+}
+
+impl FractalFloat for f32{
+    fn from_f32(value: f32) -> Self{
+        value
+    }
+
+    fn from_f64(value: f64) -> Self{
+        value as f32
+    }
+
+    fn to_f32_lossy(self) -> f32{
+        self
+    }
+}
+
+impl FractalFloat for f64{
+    fn from_f32(value: f32) -> Self{
+        value as f64
+    }
+
+    fn from_f64(value: f64) -> Self{
+        value
+    }
+
+    fn to_f32_lossy(self) -> f32{
+        self as f32
+    }
+}
+
+/// Raises the iteration limit as views get smaller: `base * (1 + k * log2(1 / width))`, clamped to
+/// `1..=max`, for a view `width` units wide.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AdaptiveLimit{
+    pub base: EscapeLimit,
+    pub k:    f32,
+    pub max:  EscapeLimit,
+}
+
+impl AdaptiveLimit{
+    /// ```
+    /// use mandelbrot::compute::AdaptiveLimit;
+    ///
+    /// let adaptive = AdaptiveLimit{base: 256, k: 0.5, max: 4096};
+    /// assert_eq!(adaptive.limit_for(1.0), 256);
+    /// // 1024 times narrower is ten halvings: 256 * (1 + 0.5 * 10).
+    /// assert_eq!(adaptive.limit_for(1.0 / 1024.0), 1536);
+    /// assert!(adaptive.limit_for(1e-3) > adaptive.limit_for(0.5));
+    /// assert_eq!(adaptive.limit_for(1e-30), 4096);
+    /// // Wide views bottom out at a single iteration.
+    /// assert_eq!(adaptive.limit_for(16.0), 1);
+    /// ```
+    pub fn limit_for(&self, width: f32) -> EscapeLimit{
+        let scale = 1.0 + self.k * (1.0 / width).log2();
+        let limit = (self.base as f32 * scale).round();
+        if limit.is_nan() {
+            return self.base.clamp(1, self.max.max(1));
+        }
+        limit.clamp(1.0, self.max.max(1) as f32) as EscapeLimit
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Precision{
+    Single,
+    Double,
+}
+
+impl Precision{
+    /// Picks `Double` once the spacing between pixels gets within a few ulps of the f32 coordinates,
+    /// which is where single precision renders start looking blocky.
+    pub fn for_rect(rect: &Rect<f32>, resolution: &Resolution) -> Precision{
+        let magnitude = [rect.x.min, rect.x.max, rect.y.min, rect.y.max]
+            .iter()
+            .fold(1.0_f32, |acc, v| acc.max(v.abs()));
+
+        let pixel_width  = (rect.x.max - rect.x.min).abs() / resolution.width as f32;
+        let pixel_height = (rect.y.max - rect.y.min).abs() / resolution.height as f32;
+
+        if pixel_width.min(pixel_height) < magnitude * f32::EPSILON * 4.0 {
+            Precision::Double
+        } else {
+            Precision::Single
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum FractalKind{
+    #[default]
+    Mandelbrot,
+    /// Iterates `z = z*z + k` starting from the pixel coordinate.
+    Julia(Complex<f32>),
+    /// Iterates `z = (|Re(z)| + i|Im(z)|)^2 + c`.
+    ///
+    /// The ship it is named after sits below the real axis near -1.75, where the Mandelbrot set has
+    /// only thin filaments, and has no mirror image above the axis:
+    ///
+    /// ```
+    /// use mandelbrot::compute::{calculate_escape_time, EscapeParams, FractalKind};
+    /// use num::Complex;
+    ///
+    /// let ship = EscapeParams{kind: FractalKind::BurningShip, ..EscapeParams::default()};
+    /// let c = Complex{re: -1.75, im: -0.02};
+    /// assert_eq!(calculate_escape_time(c, 1000, ship), None);
+    /// assert_eq!(calculate_escape_time(c, 1000, EscapeParams::default()), Some(13));
+    /// assert!(calculate_escape_time(c.conj(), 1000, ship).is_some());
+    /// ```
+    BurningShip,
+    /// Iterates `z = conj(z)^2 + c`, also known as the Mandelbar set.
+    ///
+    /// Turning the plane a third of the way around maps the set onto itself:
+    ///
+    /// ```
+    /// use mandelbrot::compute::{calculate_escape_time, EscapeParams, FractalKind};
+    /// use num::Complex;
+    ///
+    /// let tricorn = EscapeParams{kind: FractalKind::Tricorn, ..EscapeParams::default()};
+    /// let third = Complex::from_polar(1.0_f32, std::f32::consts::TAU / 3.0);
+    /// let (mut same, mut inside, mut total) = (0, 0, 0);
+    /// for y in -20..=20 {
+    ///     for x in -20..=20 {
+    ///         let c = Complex{re: x as f32 * 0.09 + 0.005, im: y as f32 * 0.09 + 0.003};
+    ///         let escape_time = calculate_escape_time(c, 100, tricorn);
+    ///         same += (calculate_escape_time(c * third, 100, tricorn) == escape_time) as u32;
+    ///         inside += escape_time.is_none() as u32;
+    ///         total += 1;
+    ///     }
+    /// }
+    /// // Rounding in the turn moves a few points on the boundary across it.
+    /// assert!(same as f32 > total as f32 * 0.95 && inside > 0, "{same} of {total}");
+    /// // The Mandelbrot set has no such symmetry.
+    /// let c = Complex{re: -1.0, im: 0.0};
+    /// assert_ne!(calculate_escape_time(c, 100, EscapeParams::default()), calculate_escape_time(c * third, 100, EscapeParams::default()));
+    /// ```
+    Tricorn,
+}
+
+/// Written as `mandelbrot`, `burning-ship`, `tricorn` or `julia:<re>,<im>`.
+impl fmt::Display for FractalKind{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            FractalKind::Mandelbrot => write!(f, "mandelbrot"),
+            FractalKind::Julia(k) => write!(f, "julia:{},{}", k.re, k.im),
+            FractalKind::BurningShip => write!(f, "burning-ship"),
+            FractalKind::Tricorn => write!(f, "tricorn"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFractalKindError(pub String);
+
+impl fmt::Display for ParseFractalKindError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "unknown fractal kind `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseFractalKindError{}
+
+impl FromStr for FractalKind{
+    type Err = ParseFractalKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>{
+        let err = || ParseFractalKindError(s.to_string());
+        match s {
+            "mandelbrot" => Ok(FractalKind::Mandelbrot),
+            "burning-ship" => Ok(FractalKind::BurningShip),
+            "tricorn" => Ok(FractalKind::Tricorn),
+            _ => {
+                let (re, im) = s.strip_prefix("julia:").and_then(|k| k.split_once(',')).ok_or_else(err)?;
+                let re = re.trim().parse().map_err(|_| err())?;
+                let im = im.trim().parse().map_err(|_| err())?;
+                Ok(FractalKind::Julia(Complex::<f32>{re, im}))
+            }
+        }
+    }
+}
+
+impl FractalKind{
+    /// Whether the fractal is its own mirror image across the real axis, so a point and its
+    /// conjugate share an escape time.
+    pub fn is_symmetric_about_real_axis(&self) -> bool{
+        match self {
+            FractalKind::Mandelbrot | FractalKind::Tricorn => true,
+            FractalKind::Julia(k) => k.im == 0.0,
+            FractalKind::BurningShip => false,
+        }
+    }
+
+    fn step<F: FractalFloat>(&self, z: Complex<F>, c: Complex<F>, exponent: u32) -> Complex<F>{
+        let z = match self {
+            FractalKind::Mandelbrot | FractalKind::Julia(_) => z,
+            FractalKind::BurningShip => Complex::<F>{re: z.re.abs(), im: z.im.abs()},
+            FractalKind::Tricorn => z.conj(),
+        };
+        if exponent == 2 {
+            z * z + c
+        } else {
+            pow(z, exponent as usize) + c
+        }
+    }
+}
+
+/// Declares a point inside the set once its orbit comes back within `epsilon` of a reference value,
+/// which is refreshed every `interval` iterations.
+///
+/// It only stops interior orbits early, every pixel keeps the result of the plain iteration:
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Periodicity, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+///
+/// let resolution = Resolution{width: 120, height: 80};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.0, 1.0).unwrap();
+/// let plain = EscapeParams{cardioid_check: false, ..EscapeParams::default()};
+/// let periodic = EscapeParams{periodicity: Some(Periodicity::default()), ..plain};
+/// let render = |params| make_calculations(resolution, rect, 1000, Precision::Single, params).unwrap().collect::<Vec<_>>();
+/// let expected = render(plain);
+/// assert!(expected.iter().any(|(_, escape_time)| escape_time.is_none()));
+/// assert_eq!(render(periodic), expected);
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Periodicity{
+    pub interval: EscapeLimit,
+    pub epsilon: f32,
+}
+
+impl Default for Periodicity{
+    fn default() -> Self{
+        Periodicity{interval: 20, epsilon: 1e-6}
+    }
+}
+
+/// Squared norm under which `Convergence::Auto` takes an orbit as captured.
+pub const DEFAULT_CONVERGENCE_EPSILON: f32 = 10e-6;
+
+/// Highest limit `Convergence::Auto` applies the shortcut at. Above it orbits have the iterations to
+/// leave again after passing close to zero, and the shortcut only hides them.
+pub const CONVERGENCE_LIMIT: EscapeLimit = 1024;
+
+/// Shortcut declaring a point inside once its orbit comes within a squared norm of zero, as it does
+/// falling into an attracting cycle through zero. It is a guess, not a membership test: orbits of
+/// points just outside the set pass close to zero too and would escape later on.
+///
+/// ```
+/// use mandelbrot::compute::{calculate_escape_time, Convergence, EscapeParams};
+/// use num::Complex;
+///
+/// // Outside the set, but the orbit passes within the default epsilon of zero before it escapes.
+/// let c = Complex{re: -1.629, im: 0.014};
+/// let with = |convergence| EscapeParams{convergence, ..EscapeParams::default()};
+/// assert_eq!(calculate_escape_time(c, 256, with(Convergence::Auto)), None);
+/// assert_eq!(calculate_escape_time(c, 256, with(Convergence::Epsilon(1e-12))), Some(65));
+/// assert_eq!(calculate_escape_time(c, 256, with(Convergence::Off)), Some(65));
+/// // High limits drop the shortcut on their own.
+/// assert_eq!(calculate_escape_time(c, 2048, with(Convergence::Auto)), Some(65));
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Convergence{
+    Off,
+    /// `DEFAULT_CONVERGENCE_EPSILON` up to `CONVERGENCE_LIMIT` iterations, off above.
+    #[default]
+    Auto,
+    /// Stops at this squared norm whatever the limit.
+    Epsilon(f32),
+}
+
+impl Convergence{
+    /// Squared norm the shortcut stops at when iterating up to `limit`, `None` without the shortcut.
+    pub fn epsilon(self, limit: EscapeLimit) -> Option<f32>{
+        match self {
+            Convergence::Off => None,
+            Convergence::Auto => (limit <= CONVERGENCE_LIMIT).then_some(DEFAULT_CONVERGENCE_EPSILON),
+            Convergence::Epsilon(epsilon) => Some(epsilon),
+        }
+    }
+}
+
+/// Everything about the iteration besides the point and the limit.
+///
+/// A larger bailout radius takes orbits a few more iterations to leave, while the interior stays inside:
+///
+/// ```
+/// use mandelbrot::compute::{calculate_escape_time, EscapeParams};
+/// use num::Complex;
+///
+/// let wide = EscapeParams{bailout_sqr: 128.0 * 128.0, ..EscapeParams::default()};
+/// let (mut later, mut total) = (0, 0);
+/// for i in 0..100 {
+///     // A circle through the boundary of the set.
+///     let angle = i as f32 * std::f32::consts::TAU / 100.0;
+///     let c = Complex{re: angle.cos() - 0.5, im: angle.sin()};
+///     let (default, larger) = (calculate_escape_time(c, 500, EscapeParams::default()), calculate_escape_time(c, 500, wide));
+///     if let (Some(default), Some(larger)) = (default, larger) {
+///         assert!(larger >= default);
+///         later += (larger > default) as u32;
+///         total += 1;
+///     }
+/// }
+/// assert!(total > 50 && later == total, "{later} of {total}");
+/// assert_eq!(calculate_escape_time(Complex{re: -0.2, im: 0.1}, 500, wide), None);
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EscapeParams{
+    pub kind: FractalKind,
+    /// Squared bailout radius. Larger radii give cleaner smooth coloring.
+    pub bailout_sqr: f32,
+    /// Skips iterating points inside the main cardioid and the period-2 bulb. Only applies to `Mandelbrot`.
+    ///
+    /// ```
+    /// use mandelbrot::compute::{calculate_escape_time, EscapeParams};
+    /// use num::Complex;
+    ///
+    /// let unchecked = EscapeParams{cardioid_check: false, ..EscapeParams::default()};
+    /// // Inside the cardioid and the bulb, some close to their edges.
+    /// for (re, im) in [(0.0, 0.0), (-0.5, 0.4), (0.24, 0.0), (-0.74, 0.0), (-1.0, 0.0), (-1.1, 0.2), (-1.24, 0.0)] {
+    ///     assert_eq!(calculate_escape_time(Complex{re, im}, 2000, EscapeParams::default()), None);
+    /// }
+    /// // Just past the cusp, in the neck and above the bulb.
+    /// for (re, im) in [(0.26, 0.0), (-0.75, 0.05), (-1.0, 0.3)] {
+    ///     let c = Complex{re, im};
+    ///     assert!(calculate_escape_time(c, 2000, EscapeParams::default()).is_some());
+    ///     assert_eq!(calculate_escape_time(c, 2000, EscapeParams::default()), calculate_escape_time(c, 2000, unchecked));
+    /// }
+    /// ```
+    pub cardioid_check: bool,
+    /// Cycle detection for interior points, off by default.
+    pub periodicity: Option<Periodicity>,
+    pub convergence: Convergence,
+    /// Power `d` of the iteration `z^d + c`. Anything but 2 renders a Multibrot set, which has
+    /// `d - 1` fold rotational symmetry on top of the mirror symmetry about the real axis:
+    ///
+    /// ```
+    /// use mandelbrot::compute::{calculate_escape_time, EscapeParams};
+    /// use num::Complex;
+    ///
+    /// let cubic = EscapeParams{exponent: 3, ..EscapeParams::default()};
+    /// let mut escaped = 0;
+    /// for y in -10..=10 {
+    ///     for x in -10..=10 {
+    ///         let c = Complex{re: x as f32 * 0.13, im: y as f32 * 0.11};
+    ///         let escape_time = calculate_escape_time(c, 200, cubic);
+    ///         assert_eq!(calculate_escape_time(-c, 200, cubic), escape_time);
+    ///         assert_eq!(calculate_escape_time(c.conj(), 200, cubic), escape_time);
+    ///         escaped += escape_time.is_some() as u32;
+    ///     }
+    /// }
+    /// assert!(escaped > 0 && escaped < 21 * 21);
+    /// // The quadratic set has no such symmetry.
+    /// let c = Complex{re: -1.0, im: 0.0};
+    /// assert_ne!(calculate_escape_time(c, 200, EscapeParams::default()), calculate_escape_time(-c, 200, EscapeParams::default()));
+    /// ```
+    pub exponent: u32,
+    /// Where the orbit starts. Julia sets start at the point itself and ignore it.
+    pub z0: Complex<f32>,
+    /// Which way pixel rows and columns run across the rect.
+    pub orientation: Orientation,
+}
+
+/// Direction of the pixel axes relative to the complex plane. The default puts `+re` to the right and
+/// `+im` up, as in mathematics; image row 0 then lies on `y.max`. Without `flip_y` row 0 lies on
+/// `y.min`, as tools without the flip expect.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Orientation, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+///
+/// // The top of this rect escapes at once, the bottom lies on the real axis inside the set.
+/// let rect = Rect::from_bounds(-0.1, 0.1, 0.0, 2.0).unwrap();
+/// let first_pixel = |orientation| {
+///     let params = EscapeParams{orientation, ..EscapeParams::default()};
+///     make_calculations(Resolution{width: 4, height: 4}, rect, 64, Precision::Single, params).unwrap().next().unwrap().1
+/// };
+/// assert!(first_pixel(Orientation::default()).is_some());
+/// assert_eq!(first_pixel(Orientation{flip_x: false, flip_y: false}), None);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Orientation{
+    /// Column 0 lies on `x.max` instead of `x.min`.
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Default for Orientation{
+    fn default() -> Self{
+        Orientation{flip_x: false, flip_y: true}
+    }
+}
+
+impl Default for EscapeParams{
+    fn default() -> Self{
+        EscapeParams{
+            kind: FractalKind::Mandelbrot,
+            bailout_sqr: 4.0,
+            cardioid_check: true,
+            periodicity: None,
+            convergence: Convergence::Auto,
+            exponent: 2,
+            z0: Complex{re: 0.0, im: 0.0},
+            orientation: Orientation::default(),
+        }
+    }
+}
+
+pub(crate) fn in_cardioid_or_bulb<F: FractalFloat>(c: Complex<F>) -> bool{
+    let quarter = F::from_f64(0.25);
+    let im_sqr = c.im * c.im;
+
+    let x = c.re - quarter;
+    let q = x * x + im_sqr;
+    let in_cardioid = q * (q + x) <= quarter * im_sqr;
+
+    let x = c.re + F::one();
+    let in_bulb = x * x + im_sqr <= F::from_f64(1.0 / 16.0);
+
+    in_cardioid || in_bulb
+}
+
+/// Escape count of an orbit, where it escaped, and with `DERIVATIVE` also `dz/dc` there (zero otherwise).
+/// `visit` sees every iterate after the starting point.
+fn iterate<F: FractalFloat, const DERIVATIVE: bool>(c: Complex<F>, limit: EscapeLimit, params: EscapeParams, visit: impl FnMut(Complex<F>))
+                     -> (Option<EscapeLimit>, Complex<F>, Complex<F>){
+    iterate_with::<F, DERIVATIVE>(c, limit, params, |z, c| params.kind.step(z, c, params.exponent), visit)
+}
+
+/// `iterate` with the formula taken from `step`, called with the current `z` and the constant.
+fn iterate_with<F: FractalFloat, const DERIVATIVE: bool>(
+        c: Complex<F>,
+        limit: EscapeLimit,
+        params: EscapeParams,
+        step: impl Fn(Complex<F>, Complex<F>) -> Complex<F>,
+        mut visit: impl FnMut(Complex<F>),
+    ) -> (Option<EscapeLimit>, Complex<F>, Complex<F>){
+    let bailout_sqr = F::from_f32(params.bailout_sqr);
+    let convergence_epsilon = params.convergence.epsilon(limit).map(F::from_f32);
+
+    // The cardioid and bulb shapes only describe orbits starting at zero.
+    let is_quadratic_mandelbrot = params.kind == FractalKind::Mandelbrot && params.exponent == 2
+        && params.z0 == Complex{re: 0.0, im: 0.0};
+    let zero = Complex::<F> {re: F::zero(), im: F::zero()};
+    // NaN compares false against both the bailout and convergence tests and would count as inside.
+    if !c.re.is_finite() || !c.im.is_finite() {
+        return (Some(0), c, zero);
+    }
+    if params.cardioid_check && is_quadratic_mandelbrot && in_cardioid_or_bulb(c) {
+        return (None, zero, zero);
+    }
+
+    let (mut z, c) = match params.kind {
+        FractalKind::Mandelbrot | FractalKind::BurningShip | FractalKind::Tricorn =>
+            (Complex::<F> {re: F::from_f32(params.z0.re), im: F::from_f32(params.z0.im)}, c),
+        FractalKind::Julia(k) => (c, Complex::<F> {re: F::from_f32(k.re), im: F::from_f32(k.im)}),
+    };
+    let periodicity = params.periodicity.map(|p| (p.interval.max(1), F::from_f32(p.epsilon * p.epsilon)));
+    let mut reference = z;
+
+    // Julia sets differentiate by the starting point instead of `c`, so `dz` starts at one and gains no `+ 1`.
+    let (mut dz, dc) = match params.kind {
+        FractalKind::Julia(_) => (Complex::<F> {re: F::one(), im: F::zero()}, zero),
+        _ => (zero, Complex::<F> {re: F::one(), im: F::zero()}),
+    };
+    let exponent = F::from_f64(params.exponent as f64);
+
+    // Tiny orbits are taken as captured by an attracting cycle, but never on the starting point, which
+    // may be zero or, with `z0`, anywhere at all.
+    for i in 0..limit{
+        let norm_sqr = z.norm_sqr();
+        if norm_sqr > bailout_sqr{
+            return (Some(i), z, dz);
+        } else if (i > 0) && convergence_epsilon.is_some_and(|epsilon| norm_sqr <= epsilon){
+            return (None, z, dz);
+        } else {
+            if DERIVATIVE {
+                dz = pow(z, (params.exponent as usize).saturating_sub(1)) * dz * exponent + dc;
+            }
+            z = step(z, c);
+            visit(z);
+        }
+
+        if let Some((interval, epsilon_sqr)) = periodicity {
+            if (z - reference).norm_sqr() <= epsilon_sqr {
+                return (None, z, dz);
+            }
+            if (i + 1) % interval == 0 {
+                reference = z;
+            }
+        }
+    }
+    (None, z, dz)
+}
+
+/// Iterations it takes the orbit of `c` to leave the bailout radius, `None` if it stays within the
+/// limit.
+///
+/// The count is the index of the first iterate outside the radius, the starting point being iterate
+/// 0. Orbits start at `z0 = 0` by default, so a `c` outside the radius escapes at 1, and only a
+/// starting point already outside it, a Julia point or `z0`, or a non-finite `c` escapes at 0. The
+/// convergence shortcut skips iterate 0 for the same reason: the default start is zero itself. An
+/// escape at 0 is the fastest there is and `write_data` colors it white, opposite the black interior.
+///
+/// ```
+/// use mandelbrot::compute::{calculate_escape_time, EscapeParams, FractalKind};
+/// use num::Complex;
+///
+/// // Just outside the radius, `c` is the first iterate after zero.
+/// assert_eq!(calculate_escape_time(Complex{re: 2.0001, im: 0.0}, 500, EscapeParams::default()), Some(1));
+/// assert_eq!(calculate_escape_time(Complex{re: 0.0, im: -2.0001}, 500, EscapeParams::default()), Some(1));
+/// // On the radius it is not outside yet, and 2 + 2 is.
+/// assert_eq!(calculate_escape_time(Complex{re: 2.0, im: 0.0}, 500, EscapeParams::default()), Some(2));
+/// // Julia orbits start at the point itself.
+/// let julia = EscapeParams{kind: FractalKind::Julia(Complex{re: -0.8, im: 0.156}), ..EscapeParams::default()};
+/// assert_eq!(calculate_escape_time(Complex{re: 2.0001, im: 0.0}, 500, julia), Some(0));
+///
+/// let from_zero = EscapeParams{z0: Complex{re: 0.0, im: 0.0}, ..EscapeParams::default()};
+/// for c in [Complex{re: -0.75, im: 0.1}, Complex{re: 0.3, im: 0.5}, Complex{re: -1.0, im: 0.0}] {
+///     assert_eq!(calculate_escape_time(c, 500, from_zero), calculate_escape_time(c, 500, EscapeParams::default()));
+/// }
+/// // A start outside the bailout radius escapes before the first step.
+/// let far = EscapeParams{z0: Complex{re: 3.0, im: 0.0}, ..EscapeParams::default()};
+/// assert_eq!(calculate_escape_time(Complex{re: -1.0, im: 0.0}, 500, far), Some(0));
+/// ```
+pub fn calculate_escape_time(c: Complex<f32>, limit: EscapeLimit, params: EscapeParams) -> Option<EscapeLimit>{
+    calculate_escape_time_generic(c, limit, params)
+}
+
+/// `calculate_escape_time` in any `FractalFloat`. In `f32` it is the plain `z = z² + c` loop, with the
+/// shortcuts turned off:
+///
+/// ```
+/// use mandelbrot::compute::{calculate_escape_time_generic, Convergence, EscapeParams};
+/// use num::Complex;
+///
+/// fn plain(c: Complex<f32>, limit: u16) -> Option<u16>{
+///     let mut z = Complex{re: 0.0_f32, im: 0.0};
+///     for i in 0..limit {
+///         if z.norm_sqr() > 4.0 {
+///             return Some(i);
+///         }
+///         z = z * z + c;
+///     }
+///     None
+/// }
+///
+/// let params = EscapeParams{cardioid_check: false, convergence: Convergence::Off, ..EscapeParams::default()};
+/// for y in 0..40 {
+///     for x in 0..60 {
+///         let c = Complex{re: -2.2 + x as f32 * 0.05, im: -1.2 + y as f32 * 0.06};
+///         assert_eq!(calculate_escape_time_generic(c, 300, params), plain(c, 300));
+///     }
+/// }
+/// ```
+pub fn calculate_escape_time_generic<F: FractalFloat>(c: Complex<F>, limit: EscapeLimit, params: EscapeParams) -> Option<EscapeLimit>{
+    iterate::<F, false>(c, limit, params, |_| {}).0
+}
+
+/// `calculate_escape_time` iterating `z = step(z, c)` in place of the formula of `params.kind`, for
+/// trying out other formulas. The kind still picks the starting point and constant, so a Julia kind
+/// starts from `c` and passes its `k` to `step`. The cardioid check is skipped, being specific to
+/// the quadratic Mandelbrot set.
+///
+/// ```
+/// use mandelbrot::compute::{calculate_escape_time_with_step, EscapeParams};
+/// use num::Complex;
+///
+/// let cubic = |c: Complex<f32>, limit| {
+///     let mut z = Complex::new(0.0, 0.0);
+///     for i in 0..limit {
+///         if z.norm_sqr() > 4.0 {
+///             return Some(i);
+///         }
+///         z = z * z * z + c;
+///     }
+///     None
+/// };
+/// for c in [Complex::new(0.5, 0.0), Complex::new(-0.1, 0.1), Complex::new(0.3, 0.6), Complex::new(-1.5, 0.2), Complex::new(0.0, 0.0)] {
+///     let stepped = calculate_escape_time_with_step(c, 200, EscapeParams::default(), |z, c| z * z * z + c);
+///     assert_eq!(stepped, cubic(c, 200));
+/// }
+/// ```
+pub fn calculate_escape_time_with_step(
+        c: Complex<f32>,
+        limit: EscapeLimit,
+        params: EscapeParams,
+        step: impl Fn(Complex<f32>, Complex<f32>) -> Complex<f32>,
+    ) -> Option<EscapeLimit>{
+    iterate_with::<f32, false>(c, limit, EscapeParams{cardioid_check: false, ..params}, step, |_| {}).0
+}
+
+/// `calculate_escape_time_smooth_generic` in f32. Colored along a line outside the set, the integer
+/// counts come out as flat bands with steps in between, the smooth ones as a gradual ramp:
+///
+/// ```
+/// use mandelbrot::color::write_l16_data;
+/// use mandelbrot::compute::{calculate_escape_time, calculate_escape_time_smooth, EscapeParams};
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{Image, PixelFormat};
+/// use num::Complex;
+///
+/// let params = EscapeParams::default();
+/// let points = || (0..200_u32).map(|y| ((0, y), Complex{re: 0.6, im: -0.5 + y as f32 * 0.005}));
+/// let colored = |data: Vec<((u32, u32), Option<f32>)>| {
+///     let mut image = Image::with_format(&Resolution{width: 1, height: 200}, PixelFormat::L16);
+///     write_l16_data(&mut image, data.into_iter(), 64, 1.0).unwrap();
+///     image.data.chunks(2).map(|sample| u16::from_be_bytes([sample[0], sample[1]])).collect::<Vec<_>>()
+/// };
+/// let integer = colored(points().map(|(p, c)| (p, calculate_escape_time(c, 64, params).map(f32::from))).collect());
+/// let smooth = colored(points().map(|(p, c)| (p, calculate_escape_time_smooth(c, 64, params))).collect());
+///
+/// // Neighbours of the same level lie within one band.
+/// let banded = |levels: &[u16]| levels.windows(2).filter(|pair| pair[0] == pair[1]).count();
+/// let largest_step = |levels: &[u16]| levels.windows(2).map(|pair| pair[0].abs_diff(pair[1])).max().unwrap();
+/// assert!(banded(&smooth) * 4 < banded(&integer));
+/// assert!(largest_step(&smooth) * 4 < largest_step(&integer));
+/// ```
+pub fn calculate_escape_time_smooth(c: Complex<f32>, limit: EscapeLimit, params: EscapeParams) -> Option<f32>{
+    calculate_escape_time_smooth_generic(c, limit, params)
+}
+
+/// Fractional escape count `i + 1 - log_d(log2(|z|))`, which removes the banding of the integer count.
+pub fn calculate_escape_time_smooth_generic<F: FractalFloat>(c: Complex<F>, limit: EscapeLimit, params: EscapeParams) -> Option<f32>{
+    let (escape, z, _) = iterate::<F, false>(c, limit, params, |_| {});
+    escape.map(|i| {
+        let log_norm = z.norm().log2();
+        let correction = if params.exponent == 2 {
+            log_norm.log2()
+        } else {
+            log_norm.ln() / F::from_f64(params.exponent as f64).ln()
+        };
+        let smooth = F::from_f64((i + 1) as f64) - correction;
+        smooth.to_f32_lossy().max(0.0)
+    })
+}
+
+/// Escape count of a point together with its estimated distance to the set's boundary.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EscapeResult{
+    pub escape_time: Option<EscapeLimit>,
+    /// `None` for points inside the set.
+    pub distance: Option<f32>,
+}
+
+/// `calculate_escape_time_with_distance_generic` in f32. The distance goes to zero along with the
+/// actual distance to the set, here on the way to its tip at -2:
+///
+/// ```
+/// use mandelbrot::compute::{calculate_escape_time_with_distance, EscapeParams};
+/// use num::Complex;
+///
+/// // The estimate needs the orbit well past the default radius to be accurate.
+/// let params = EscapeParams{bailout_sqr: 1e8, ..EscapeParams::default()};
+/// for gap in [0.1, 0.01, 0.001, 0.0001] {
+///     let c = Complex{re: -2.0 - gap, im: 0.0};
+///     let distance = calculate_escape_time_with_distance(c, 10000, params).distance.unwrap();
+///     assert!(distance > gap / 4.0 && distance < gap * 4.0, "{distance} at {gap} from the tip");
+/// }
+/// assert_eq!(calculate_escape_time_with_distance(Complex{re: -1.0, im: 0.0}, 10000, params).distance, None);
+/// ```
+pub fn calculate_escape_time_with_distance(c: Complex<f32>, limit: EscapeLimit, params: EscapeParams) -> EscapeResult{
+    calculate_escape_time_with_distance_generic(c, limit, params)
+}
+
+/// Exterior distance estimate `|z| * ln(|z|) / |dz|`, iterating the derivative `dz = d*z^(d-1)*dz + 1`
+/// alongside `z`. A larger `bailout_sqr` makes the estimate more accurate. For `BurningShip` and
+/// `Tricorn` the fold and conjugation are ignored, so their distances are only rough.
+pub fn calculate_escape_time_with_distance_generic<F: FractalFloat>(c: Complex<F>, limit: EscapeLimit, params: EscapeParams) -> EscapeResult{
+    let (escape_time, z, dz) = iterate::<F, true>(c, limit, params, |_| {});
+    let distance = escape_time.map(|_| {
+        let norm = z.norm();
+        let distance = norm * norm.ln() / dz.norm();
+        distance.to_f32_lossy().max(0.0)
+    });
+    EscapeResult{escape_time, distance}
+}
+
+/// Where the orbit of a point inside the set went, for shading the interior.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct InteriorData{
+    /// `|z|` after the last iteration.
+    pub final_magnitude: f32,
+    /// Average of the iterates after the starting point.
+    pub mean_position:   Complex<f32>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EscapeOutcome{
+    Escaped(EscapeLimit),
+    Inside(InteriorData),
+}
+
+pub fn calculate_escape_outcome(c: Complex<f32>, limit: EscapeLimit, params: EscapeParams) -> EscapeOutcome{
+    calculate_escape_outcome_generic(c, limit, params)
+}
+
+/// Escape count, or `InteriorData` for points that never escape. The interior has to be iterated for
+/// that, so the cardioid check is skipped.
+pub fn calculate_escape_outcome_generic<F: FractalFloat>(c: Complex<F>, limit: EscapeLimit, params: EscapeParams) -> EscapeOutcome{
+    let params = EscapeParams{cardioid_check: false, ..params};
+    let mut sum = Complex::<F>{re: F::zero(), im: F::zero()};
+    let mut count = 0_u32;
+    let (escape_time, z, _) = iterate::<F, false>(c, limit, params, |z| {
+        sum = sum + z;
+        count += 1;
+    });
+
+    match escape_time {
+        Some(escape_time) => EscapeOutcome::Escaped(escape_time),
+        None => {
+            let mean = sum / F::from_f64(count.max(1) as f64);
+            EscapeOutcome::Inside(InteriorData{
+                final_magnitude: z.norm().to_f32_lossy(),
+                mean_position:   Complex::<f32>{re: mean.re.to_f32_lossy(), im: mean.im.to_f32_lossy()},
+            })
+        }
+    }
+}
+
+/// Shape an orbit trap measures the orbit's distance to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OrbitTrap{
+    Point(Complex<f32>),
+    /// The real and imaginary axes.
+    Cross,
+    Circle{center: Complex<f32>, radius: f32},
+}
+
+impl OrbitTrap{
+    fn distance<F: FractalFloat>(&self, z: Complex<F>) -> F{
+        let complex = |c: Complex<f32>| Complex::<F>{re: F::from_f32(c.re), im: F::from_f32(c.im)};
+        match *self {
+            OrbitTrap::Point(point) => (z - complex(point)).norm(),
+            OrbitTrap::Cross => z.re.abs().min(z.im.abs()),
+            OrbitTrap::Circle{center, radius} => ((z - complex(center)).norm() - F::from_f32(radius)).abs(),
+        }
+    }
+}
+
+pub fn calculate_orbit_trap(c: Complex<f32>, limit: EscapeLimit, params: EscapeParams, trap: OrbitTrap) -> f32{
+    calculate_orbit_trap_generic(c, limit, params, trap)
+}
+
+/// Closest the orbit of `c` comes to `trap`, over every iterate after the starting point. Interior
+/// points are iterated too, so the cardioid check is skipped.
+///
+/// ```
+/// use mandelbrot::compute::{calculate_orbit_trap, EscapeParams, OrbitTrap};
+/// use num::Complex;
+///
+/// let origin = OrbitTrap::Point(Complex{re: 0.0, im: 0.0});
+/// // The orbit 1, 2, 5 of `c = 1` comes no closer to the origin than 1, as the starting 0 doesn't count.
+/// assert_eq!(calculate_orbit_trap(Complex{re: 1.0, im: 0.0}, 256, EscapeParams::default(), origin), 1.0);
+/// // It lands exactly on 2.
+/// assert_eq!(calculate_orbit_trap(Complex{re: 1.0, im: 0.0}, 256, EscapeParams::default(), OrbitTrap::Point(Complex{re: 2.0, im: 0.0})), 0.0);
+/// // `c = i` cycles through i, -1 + i, -i, staying a unit away from the origin.
+/// assert_eq!(calculate_orbit_trap(Complex{re: 0.0, im: 1.0}, 256, EscapeParams::default(), origin), 1.0);
+/// // `c = -1` cycles between -1 and 0.
+/// assert_eq!(calculate_orbit_trap(Complex{re: -1.0, im: 0.0}, 256, EscapeParams::default(), origin), 0.0);
+/// ```
+pub fn calculate_orbit_trap_generic<F: FractalFloat>(c: Complex<F>, limit: EscapeLimit, params: EscapeParams, trap: OrbitTrap) -> f32{
+    let params = EscapeParams{cardioid_check: false, ..params};
+    let mut min_distance = F::infinity();
+    iterate::<F, false>(c, limit, params, |z| min_distance = min_distance.min(trap.distance(z)));
+    min_distance.to_f32_lossy()
+}
+
+/// Stripe frequency giving the usual look of stripe average coloring.
+pub const DEFAULT_STRIPE_FREQUENCY: f32 = 5.0;
+
+pub fn calculate_stripe_average(c: Complex<f32>, limit: EscapeLimit, params: EscapeParams, frequency: f32) -> Option<f32>{
+    calculate_stripe_average_generic(c, limit, params, frequency)
+}
+
+/// Stripe average coloring: the mean of `(sin(frequency * arg(z)) + 1) / 2` over the orbit of an
+/// escaping point, from 0 to 1. The means with and without the last iterate are blended by the smooth
+/// escape count's fraction so the stripes flow across the iteration bands. `None` inside the set.
+///
+/// ```
+/// use mandelbrot::compute::{calculate_stripe_average, EscapeParams, DEFAULT_STRIPE_FREQUENCY};
+/// use num::Complex;
+///
+/// let c = Complex{re: -0.75, im: 0.1};
+/// let stripes = calculate_stripe_average(c, 256, EscapeParams::default(), DEFAULT_STRIPE_FREQUENCY).unwrap();
+/// assert!((0.0..=1.0).contains(&stripes));
+/// // The same orbit always sums to the same value.
+/// assert_eq!(calculate_stripe_average(c, 256, EscapeParams::default(), DEFAULT_STRIPE_FREQUENCY), Some(stripes));
+/// // The orbit 1, 2, 5 of `c = 1` stays on the real axis, where every term is a half.
+/// assert_eq!(calculate_stripe_average(Complex{re: 1.0, im: 0.0}, 256, EscapeParams::default(), 5.0), Some(0.5));
+/// assert_eq!(calculate_stripe_average(Complex{re: 0.0, im: 0.0}, 256, EscapeParams::default(), 5.0), None);
+/// ```
+pub fn calculate_stripe_average_generic<F: FractalFloat>(c: Complex<F>, limit: EscapeLimit, params: EscapeParams, frequency: f32) -> Option<f32>{
+    let frequency = F::from_f32(frequency);
+    let half = F::from_f64(0.5);
+    let (mut sum, mut last, mut count) = (F::zero(), F::zero(), 0_u32);
+    let (escape, z, _) = iterate::<F, false>(c, limit, params, |z| {
+        last = (frequency * z.arg()).sin() * half + half;
+        sum = sum + last;
+        count += 1;
+    });
+    escape?;
+
+    let average = sum / F::from_f64(count.max(1) as f64);
+    if count < 2 {
+        return Some(average.to_f32_lossy());
+    }
+    let previous = (sum - last) / F::from_f64((count - 1) as f64);
+    // How far past the bailout radius the last iterate landed, 0 just past it and 1 a whole band further.
+    let log_ratio = z.norm().ln() / F::from_f32(params.bailout_sqr.sqrt()).ln();
+    let overshoot = log_ratio.ln() / F::from_f64(params.exponent.max(2) as f64).ln();
+    let fraction = (F::one() - overshoot).max(F::zero()).min(F::one());
+    Some((previous + (average - previous) * fraction).to_f32_lossy())
+}
+
+pub fn orbit(c: Complex<f32>, limit: EscapeLimit, params: EscapeParams) -> Vec<Complex<f32>>{
+    orbit_generic(c, limit, params)
+}
+
+/// Every iterate `z` of `c` after the starting point, in order, ending with the first one past the
+/// bailout if the point escapes. Interior points are iterated too, so the cardioid check is skipped.
+///
+/// ```
+/// use mandelbrot::compute::{orbit, EscapeParams};
+/// use num::Complex;
+///
+/// let inside = orbit(Complex{re: 0.0, im: 0.0}, 100, EscapeParams::default());
+/// assert!(inside.iter().all(|z| z.norm() <= 2.0));
+///
+/// let escaping = orbit(Complex{re: 0.5, im: 0.5}, 100, EscapeParams::default());
+/// assert!(escaping.last().unwrap().norm() > 2.0);
+/// ```
+pub fn orbit_generic<F: FractalFloat>(c: Complex<F>, limit: EscapeLimit, params: EscapeParams) -> Vec<Complex<F>>{
+    let params = EscapeParams{cardioid_check: false, ..params};
+    let mut orbit = Vec::new();
+    iterate::<F, false>(c, limit, params, |z| orbit.push(z));
+    orbit
+}
+
+/// Maps a (possibly fractional) pixel position to its complex coordinate. In `Single` precision the
+/// coordinates are computed in f32 and only widened, so narrowing them back is lossless.
+pub(crate) fn coordinate_mapper(resolution: Resolution, rect: Rect<f32>, precision: Precision, orientation: Orientation)
+                     -> impl Fn(f64, f64) -> Complex<f64> {
+    let Rect{x: Range{min: x_min, max: x_max} ,y: Range{min: y_min, max: y_max}} = rect;
+    let oriented = |length: u32, flip: bool| if flip { (length as f64, 0_f64) } else { (0_f64, length as f64) };
+    let (x_from, x_to) = oriented(resolution.width, orientation.flip_x);
+    let (y_from, y_to) = oriented(resolution.height, orientation.flip_y);
+
+    let x_lerp = make_lerp((x_from as f32, x_to as f32), (x_min, x_max));
+    let y_lerp = make_lerp((y_from as f32, y_to as f32), (y_min, y_max));
+
+    let x_lerp_f64 = make_lerp((x_from, x_to), (x_min as f64, x_max as f64));
+    let y_lerp_f64 = make_lerp((y_from, y_to), (y_min as f64, y_max as f64));
+
+    // A rect centered on the real axis maps the lower half as the exact conjugates of the upper half,
+    // which rounding in the lerp would otherwise miss by an ulp, so `render_parallel` can mirror it.
+    let mirrored = y_min == -y_max;
+    let height = resolution.height as f64;
+
+    move |x, y| {
+        let (y, sign) = if mirrored && y > height / 2.0 { (height - y, -1.0) } else { (y, 1.0) };
+        match precision {
+            Precision::Single => Complex::<f64>{re: x_lerp(x as f32) as f64, im: sign * y_lerp(y as f32) as f64},
+            Precision::Double => Complex::<f64>{re: x_lerp_f64(x), im: sign * y_lerp_f64(y)},
+        }
+    }
+}
+
+/// Pixel positions in raster order, matching the row-major layout of `Image` and `EscapeGrid`.
+fn pixels(resolution: Resolution) -> impl Iterator<Item = (u32, u32)> {
+    let width = resolution.width;
+    (0..resolution.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+}
+
+/// Complex coordinate of every pixel, row by row, the first stage of `make_calculations`; pair it with
+/// `escape_times` or feed the points to another backend. The real parts are mapped once per column
+/// and the imaginary part once per row, rather than both for every pixel. In `Single` precision the
+/// points are f32 values widened to f64, see `coordinate_mapper`.
+///
+/// ```
+/// use mandelbrot::compute::{pixel_coordinates, Orientation, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use num::Complex;
+///
+/// let rect = Rect::from_bounds(-2.0, 2.0, -1.0, 1.0).unwrap();
+/// let points: Vec<_> = pixel_coordinates(Resolution{width: 4, height: 2}, rect, Precision::Double, Orientation::default()).collect();
+/// assert_eq!(points.len(), 8);
+/// // The top left pixel sits on the top left corner, each pixel one step of 1.0 right or down.
+/// assert_eq!(points[0], ((0, 0), Complex::new(-2.0, 1.0)));
+/// assert_eq!(points[3], ((3, 0), Complex::new(1.0, 1.0)));
+/// assert_eq!(points[7], ((3, 1), Complex::new(1.0, 0.0)));
+/// ```
+pub fn pixel_coordinates(resolution: Resolution, rect: Rect<f32>, precision: Precision, orientation: Orientation)
+                     -> impl Iterator<Item = ((u32, u32), Complex<f64>)> {
+    let mapper = coordinate_mapper(resolution, rect, precision, orientation);
+    let columns: Arc<[f64]> = (0..resolution.width).map(|x| mapper(x as f64, 0.0).re).collect();
+
+    (0..resolution.height).flat_map(move |y| {
+        let im = mapper(0.0, y as f64).im;
+        let columns = Arc::clone(&columns);
+        (0..resolution.width).map(move |x| ((x, y), Complex::<f64>{re: columns[x as usize], im}))
+    })
+}
+
+pub(crate) fn narrow(c: Complex<f64>) -> Complex<f32>{
+    Complex::<f32>{re: c.re as f32, im: c.im as f32}
+}
+
+pub(crate) fn escape_time_at(c: Complex<f64>, limit: EscapeLimit, precision: Precision, params: EscapeParams) -> Option<EscapeLimit>{
+    match precision {
+        Precision::Single => calculate_escape_time(narrow(c), limit, params),
+        Precision::Double => calculate_escape_time_generic(c, limit, params),
+    }
+}
+
+/// Escape time of the single pixel at `(x, y)`, mapped exactly as `make_calculations` maps it, for
+/// rendering on demand or checking particular pixels. Fails if `rect` does not pass `Rect::validate`.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, pixel_escape, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+///
+/// let resolution = Resolution{width: 40, height: 30};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.2, 1.0).unwrap();
+/// let params = EscapeParams::default();
+/// let all: Vec<_> = make_calculations(resolution, rect, 200, Precision::Single, params).unwrap().collect();
+/// for (x, y) in [(0, 0), (39, 29), (20, 15), (29, 11), (7, 22)] {
+///     let single = pixel_escape(&resolution, &rect, x, y, 200, Precision::Single, params).unwrap();
+///     assert_eq!(all[(y * 40 + x) as usize], ((x, y), single));
+/// }
+/// ```
+pub fn pixel_escape(resolution: &Resolution, rect: &Rect<f32>, x: u32, y: u32, limit: EscapeLimit, precision: Precision, params: EscapeParams)
+                     -> Result<Option<EscapeLimit>, RectError> {
+    rect.validate()?;
+    let c = coordinate_mapper(*resolution, *rect, precision, params.orientation)(x as f64, y as f64);
+    Ok(escape_time_at(c, limit, precision, params))
+}
+
+/// Second stage of `make_calculations`: the escape time of every point of `coordinates`.
+pub fn escape_times(coordinates: impl Iterator<Item = ((u32, u32), Complex<f64>)>, limit: EscapeLimit, precision: Precision, params: EscapeParams)
+                     -> impl Iterator<Item = ((u32, u32), Option<EscapeLimit>)> {
+    coordinates.map(move |(position, c)| (position, escape_time_at(c, limit, precision, params)))
+}
+
+/// Escape time of every pixel of `rect`, row by row. Fails if `rect` does not pass `Rect::validate`.
+///
+/// ```
+/// use mandelbrot::compute::{calculate_escape_time, make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Range, Rect, RectError, Resolution};
+/// use num::Complex;
+///
+/// let line = Rect{x: Range{min: 0.5, max: 0.5}, y: Range{min: -1.0, max: 1.0}};
+/// let calculations = make_calculations(Resolution{width: 4, height: 4}, line, 64, Precision::Single, EscapeParams::default());
+/// assert!(matches!(calculations, Err(RectError::Empty{axis: "x", ..})));
+///
+/// // Non-finite points escape at once rather than passing for the inside of the set.
+/// let nan = Complex{re: f32::NAN, im: 0.0};
+/// assert_eq!(calculate_escape_time(nan, 64, EscapeParams::default()), Some(0));
+/// ```
+///
+/// Pixels come in the order the image stores them, and the image does not depend on the order:
+///
+/// ```
+/// use mandelbrot::color::write_data;
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::image::Image;
+///
+/// let resolution = Resolution{width: 40, height: 30};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.2, 1.2).unwrap();
+/// let calculations: Vec<_> = make_calculations(resolution, rect, 100, Precision::Single, EscapeParams::default()).unwrap().collect();
+/// assert!(calculations.iter().enumerate().all(|(index, ((x, y), _))| (*x + *y * 40) as usize == index));
+///
+/// let mut column_major = calculations.clone();
+/// column_major.sort_by_key(|((x, y), _)| (*x, *y));
+/// let (mut rows, mut columns) = (Image::new(&resolution), Image::new(&resolution));
+/// write_data(&mut rows, calculations.into_iter(), 100, 1.0).unwrap();
+/// write_data(&mut columns, column_major.into_iter(), 100, 1.0).unwrap();
+/// assert_eq!(rows.data, columns.data);
+/// ```
+///
+/// Every pixel matches a plain loop lerping its position straight into `rect`, with row 0 on top:
+///
+/// ```
+/// use mandelbrot::compute::{calculate_escape_time, make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{make_lerp, Rect, Resolution};
+/// use num::Complex;
+///
+/// let resolution = Resolution{width: 53, height: 31};
+/// let rect = Rect::from_bounds(-1.9, 0.6, -0.3, 1.1).unwrap();
+/// let params = EscapeParams::default();
+/// let x_lerp = make_lerp((0.0, 53.0), (rect.x.min, rect.x.max));
+/// let y_lerp = make_lerp((31.0, 0.0), (rect.y.min, rect.y.max));
+/// let mut reference = Vec::new();
+/// for y in 0..31 {
+///     for x in 0..53 {
+///         let c = Complex{re: x_lerp(x as f32), im: y_lerp(y as f32)};
+///         reference.push(((x, y), calculate_escape_time(c, 150, params)));
+///     }
+/// }
+/// let calculations: Vec<_> = make_calculations(resolution, rect, 150, Precision::Single, params).unwrap().collect();
+/// assert_eq!(calculations, reference);
+/// ```
+pub fn make_calculations(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams)
+                     -> Result<impl Iterator<Item = ((u32, u32), Option<EscapeLimit>)>, RectError> {
+    rect.validate()?;
+    Ok(escape_times(pixel_coordinates(resolution, rect, precision, params.orientation), limit, precision, params))
+}
+
+/// `make_calculations` extended by `overlap` pixels past every edge of `rect`, over a grid of
+/// `resolution` plus `2 * overlap` pixels each way. Pixel `(overlap, overlap)` sits on the corner of
+/// `rect` and the pixel spacing is unchanged, so tiles sharing an edge compute the same points along it.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations_with_overlap, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+///
+/// let resolution = Resolution{width: 16, height: 16};
+/// let tile = |x_min, x_max| {
+///     let rect = Rect::from_bounds(x_min, x_max, 0.5, 1.0).unwrap();
+///     let calculations = make_calculations_with_overlap(resolution, rect, 2, 200, Precision::Single, EscapeParams::default());
+///     calculations.unwrap().collect::<Vec<_>>()
+/// };
+/// let (left, right) = (tile(-1.0, -0.5), tile(-0.5, 0.0));
+/// assert_eq!(left.len(), 20 * 20);
+/// // The last four columns of the left tile are the first four of the right one.
+/// for y in 0..20 {
+///     for x in 0..4 {
+///         assert_eq!(left[y * 20 + 16 + x].1, right[y * 20 + x].1);
+///     }
+/// }
+/// ```
+pub fn make_calculations_with_overlap(resolution: Resolution, rect: Rect<f32>, overlap: u32, limit: EscapeLimit, precision: Precision, params: EscapeParams)
+                     -> Result<impl Iterator<Item = ((u32, u32), Option<EscapeLimit>)>, RectError> {
+    rect.validate()?;
+    let mapper = coordinate_mapper(resolution, rect, precision, params.orientation);
+    let extended = Resolution{width: resolution.width + 2 * overlap, height: resolution.height + 2 * overlap};
+    let offset = overlap as f64;
+    let coordinates = pixels(extended)
+        .map(move |(x, y)| ((x, y), mapper(x as f64 - offset, y as f64 - offset)));
+    Ok(escape_times(coordinates, limit, precision, params))
+}
+
+/// Same escape times as `make_calculations`, but one full row at a time from the top, so callers can
+/// stream rows out instead of buffering the whole image.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, make_calculations_by_row, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+///
+/// let resolution = Resolution{width: 37, height: 23};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.1, 0.9).unwrap();
+/// let params = EscapeParams::default();
+/// let rows = make_calculations_by_row(resolution, rect, 100, Precision::Single, params).unwrap();
+/// let reassembled: Vec<_> = rows
+///     .inspect(|(_, row)| assert_eq!(row.len(), 37))
+///     .flat_map(|(y, row)| row.into_iter().enumerate().map(move |(x, escape_time)| ((x as u32, y), escape_time)))
+///     .collect();
+/// let flat: Vec<_> = make_calculations(resolution, rect, 100, Precision::Single, params).unwrap().collect();
+/// assert_eq!(reassembled, flat);
+/// ```
+pub fn make_calculations_by_row(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams)
+                     -> Result<impl Iterator<Item = (u32, Vec<Option<EscapeLimit>>)>, RectError> {
+    rect.validate()?;
+    let mapper = coordinate_mapper(resolution, rect, precision, params.orientation);
+    Ok((0..resolution.height)
+        .map(move |y| {
+            let row = (0..resolution.width)
+                .map(|x| escape_time_at(mapper(x as f64, y as f64), limit, precision, params))
+                .collect();
+            (y, row)
+        }))
+}
+
+/// Consecutive pixels of `make_calculations_chunked` with their escape times.
+pub type EscapeChunk = Vec<((u32, u32), Option<EscapeLimit>)>;
+
+/// Same escape times as `make_calculations`, `chunk` pixels at a time with each chunk computed in
+/// parallel, so that a consumer encoding one chunk bounds how far ahead the computation runs. Fails as
+/// `make_calculations` does.
+///
+/// ```
+/// use std::num::NonZeroUsize;
+/// use mandelbrot::compute::{make_calculations, make_calculations_chunked, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+///
+/// let resolution = Resolution{width: 37, height: 23};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.2, 1.2).unwrap();
+/// let params = EscapeParams::default();
+/// let whole: Vec<_> = make_calculations(resolution, rect, 100, Precision::Single, params).unwrap().collect();
+/// let chunks: Vec<_> = make_calculations_chunked(resolution, rect, 100, Precision::Single, params, NonZeroUsize::new(64).unwrap()).unwrap().collect();
+/// assert!(chunks[..chunks.len() - 1].iter().all(|chunk| chunk.len() == 64));
+/// assert_eq!(chunks.concat(), whole);
+/// ```
+pub fn make_calculations_chunked(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams, chunk: NonZeroUsize)
+                     -> Result<impl Iterator<Item = EscapeChunk>, RectError> {
+    rect.validate()?;
+    let mut coordinates = pixel_coordinates(resolution, rect, precision, params.orientation);
+    Ok(std::iter::from_fn(move || {
+        let points: Vec<_> = coordinates.by_ref().take(chunk.get()).collect();
+        if points.is_empty() {
+            return None;
+        }
+        Some(points.into_par_iter()
+            .map(|(position, c)| (position, escape_time_at(c, limit, precision, params)))
+            .collect())
+    }))
+}
+
+/// Smooth escape time of every pixel, see `calculate_escape_time_smooth`. Like `make_calculations`,
+/// this and the other per-pixel calculations fail on a rect that does not pass `Rect::validate`
+/// instead of mapping pixels to NaN:
+///
+/// ```
+/// use mandelbrot::compute::{make_distance_calculations, make_smooth_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Range, Rect, RectError, Resolution};
+///
+/// let resolution = Resolution{width: 4, height: 4};
+/// let nan = Rect{x: Range{min: f32::NAN, max: 1.0}, y: Range{min: -1.0, max: 1.0}};
+/// let smooth = make_smooth_calculations(resolution, nan, 64, Precision::Single, EscapeParams::default());
+/// assert!(matches!(smooth, Err(RectError::NonFinite{..})));
+///
+/// let line = Rect{x: Range{min: -1.0, max: 1.0}, y: Range{min: 0.5, max: 0.5}};
+/// let distances = make_distance_calculations(resolution, line, 64, Precision::Single, EscapeParams::default());
+/// assert!(matches!(distances, Err(RectError::Empty{axis: "y", ..})));
+/// ```
+pub fn make_smooth_calculations(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams)
+                     -> Result<impl Iterator<Item = ((u32, u32), Option<f32>)>, RectError> {
+    rect.validate()?;
+    Ok(pixel_coordinates(resolution, rect, precision, params.orientation)
+        .map(move |(position, c)| {
+            let escape_time = match precision {
+                Precision::Single => calculate_escape_time_smooth(narrow(c), limit, params),
+                Precision::Double => calculate_escape_time_smooth_generic(c, limit, params),
+            };
+            (position, escape_time)
+        }))
+}
+
+/// Orbit trap distance of every pixel, see `calculate_orbit_trap`.
+pub fn make_orbit_trap_calculations(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams, trap: OrbitTrap)
+                     -> Result<impl Iterator<Item = ((u32, u32), f32)>, RectError> {
+    rect.validate()?;
+    Ok(pixel_coordinates(resolution, rect, precision, params.orientation)
+        .map(move |(position, c)| {
+            let distance = match precision {
+                Precision::Single => calculate_orbit_trap(narrow(c), limit, params, trap),
+                Precision::Double => calculate_orbit_trap_generic(c, limit, params, trap),
+            };
+            (position, distance)
+        }))
+}
+
+/// Stripe average of every pixel, see `calculate_stripe_average`.
+pub fn make_stripe_calculations(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams, frequency: f32)
+                     -> Result<impl Iterator<Item = ((u32, u32), Option<f32>)>, RectError> {
+    rect.validate()?;
+    Ok(pixel_coordinates(resolution, rect, precision, params.orientation)
+        .map(move |(position, c)| {
+            let stripes = match precision {
+                Precision::Single => calculate_stripe_average(narrow(c), limit, params, frequency),
+                Precision::Double => calculate_stripe_average_generic(c, limit, params, frequency),
+            };
+            (position, stripes)
+        }))
+}
+
+/// `make_calculations` with `InteriorData` for the pixels inside the set, see `calculate_escape_outcome`.
+pub fn make_escape_outcomes(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams)
+                     -> Result<impl Iterator<Item = ((u32, u32), EscapeOutcome)>, RectError> {
+    rect.validate()?;
+    Ok(pixel_coordinates(resolution, rect, precision, params.orientation)
+        .map(move |(position, c)| {
+            let outcome = match precision {
+                Precision::Single => calculate_escape_outcome(narrow(c), limit, params),
+                Precision::Double => calculate_escape_outcome_generic(c, limit, params),
+            };
+            (position, outcome)
+        }))
+}
+
+/// Estimated distance of every pixel to the set, see `calculate_escape_time_with_distance`.
+pub fn make_distance_calculations(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams)
+                     -> Result<impl Iterator<Item = ((u32, u32), Option<f32>)>, RectError> {
+    rect.validate()?;
+    Ok(pixel_coordinates(resolution, rect, precision, params.orientation)
+        .map(move |(position, c)| {
+            let result = match precision {
+                Precision::Single => calculate_escape_time_with_distance(narrow(c), limit, params),
+                Precision::Double => calculate_escape_time_with_distance_generic(c, limit, params),
+            };
+            (position, result.distance)
+        }))
+}
+
+/// Mean escape time of the sample points, counting samples inside the set as `limit`. `None` only
+/// when every sample is inside.
+fn average_samples(samples: impl Iterator<Item = Complex<f64>>, limit: EscapeLimit, precision: Precision, params: EscapeParams) -> Option<f32>{
+    let mut sum = 0_f32;
+    let mut count = 0;
+    let mut escaped = 0;
+    for c in samples {
+        match escape_time_at(c, limit, precision, params) {
+            Some(escape_time) => {
+                sum += escape_time as f32;
+                escaped += 1;
+            }
+            None => sum += limit as f32,
+        }
+        count += 1;
+    }
+    (escaped > 0).then(|| sum / count as f32)
+}
+
+/// Averages `samples * samples` evenly spaced escape times per pixel. Samples inside the set count as
+/// `limit`, and a pixel is only inside when all of its samples are. `samples = 1` matches `make_calculations`.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, make_calculations_supersampled, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+///
+/// let resolution = Resolution{width: 48, height: 32};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.0, 1.0).unwrap();
+/// let params = EscapeParams::default();
+/// let sampled = |samples| make_calculations_supersampled(resolution, rect, 100, Precision::Single, params, samples).unwrap().collect::<Vec<_>>();
+/// let single: Vec<_> = make_calculations(resolution, rect, 100, Precision::Single, params).unwrap()
+///     .map(|(position, escape_time)| (position, escape_time.map(f32::from)))
+///     .collect();
+/// assert_eq!(sampled(1), single);
+///
+/// // Four by four samples only change the pixels the boundary or a band edge runs through.
+/// let changed = sampled(4).iter().zip(&single).filter(|(a, b)| a != b).count();
+/// assert!(changed > 0 && changed < single.len() / 2, "{changed}");
+/// ```
+pub fn make_calculations_supersampled(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams, samples: u32)
+                     -> Result<impl Iterator<Item = ((u32, u32), Option<f32>)>, RectError> {
+    rect.validate()?;
+    let samples = samples.max(1);
+    let mapper = coordinate_mapper(resolution, rect, precision, params.orientation);
+
+    Ok(pixels(resolution)
+        .map(move |(x, y)| {
+            let offsets = (0..samples).flat_map(move |sub_y| (0..samples).map(move |sub_x| (sub_x, sub_y)));
+            let points = offsets.map(|(sub_x, sub_y)| mapper(
+                x as f64 + sub_x as f64 / samples as f64,
+                y as f64 + sub_y as f64 / samples as f64,
+            ));
+            ((x, y), average_samples(points, limit, precision, params))
+        }))
+}
+
+/// Like `make_calculations_supersampled`, but averages `samples` points placed at random inside each
+/// pixel, which trades the moiré of a regular grid for noise. Each pixel draws its offsets from its own
+/// generator seeded by `seed` and its position, so the output only depends on `seed`.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations_jittered, make_calculations_supersampled, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+///
+/// let resolution = Resolution{width: 32, height: 24};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.2, 1.2).unwrap();
+/// let params = EscapeParams::default();
+/// let jittered = |seed| make_calculations_jittered(resolution, rect, 128, Precision::Single, params, 16, seed).unwrap().collect::<Vec<_>>();
+/// let grid: Vec<_> = make_calculations_supersampled(resolution, rect, 128, Precision::Single, params, 4).unwrap().collect();
+///
+/// assert_eq!(jittered(7), jittered(7));
+/// assert_ne!(jittered(7), jittered(8));
+/// assert_ne!(jittered(7), grid);
+/// ```
+pub fn make_calculations_jittered(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams, samples: u32, seed: u64)
+                     -> Result<impl Iterator<Item = ((u32, u32), Option<f32>)>, RectError> {
+    rect.validate()?;
+    let samples = samples.max(1);
+    let mapper = coordinate_mapper(resolution, rect, precision, params.orientation);
+
+    Ok(pixels(resolution)
+        .map(move |(x, y)| {
+            let pixel = ((y as u64) << 32) | x as u64;
+            let mut rng = SmallRng::seed_from_u64(seed ^ pixel.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let points = (0..samples).map(|_| mapper(
+                x as f64 + rng.random::<f64>(),
+                y as f64 + rng.random::<f64>(),
+            ));
+            ((x, y), average_samples(points, limit, precision, params))
+        }))
+}
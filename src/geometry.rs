@@ -0,0 +1,243 @@
+use std::error::Error;
+use std::fmt;
+
+use num::{Complex, Float};
+use serde::{Deserialize, Serialize};
+
+/// Linear map taking `input.0` to `output.0` and `input.1` to `output.1`. An empty `input` range
+/// has no such map and yields non-finite values, see `Rect::validate`.
+pub fn make_lerp<F: Float>(input: (F, F), output: (F, F)) -> impl Fn(F) -> F {
+    let a = (output.1 - output.0) / (input.1 - input.0);
+    let b =  output.0 - (input.0 * a);
+    move |x| x*a + b
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Resolution{
+    pub width: u32, pub height: u32
+}
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Range<T>{
+    pub min: T,
+    pub max: T,
+}
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Rect<T>{
+    pub x: Range<T>,
+    pub y: Range<T>,
+}
+
+/// A range whose `min` is not smaller than its `max`, or that involves NaN.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RangeError<T>{
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: fmt::Display> fmt::Display for RangeError<T>{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "range is empty: min ({}) must be smaller than max ({})", self.min, self.max)
+    }
+}
+
+impl<T: fmt::Display + fmt::Debug> Error for RangeError<T>{}
+
+impl<T: PartialOrd> Range<T>{
+    /// Range from `min` to `max`, which must be strictly ordered. Reversed bounds are rejected rather
+    /// than swapped, since they usually mean the caller mixed up its arguments.
+    ///
+    /// ```
+    /// use mandelbrot::geometry::{Range, RangeError};
+    ///
+    /// assert_eq!(Range::new(-2.0, 1.0), Ok(Range{min: -2.0, max: 1.0}));
+    /// assert_eq!(Range::new(1.0, -2.0), Err(RangeError{min: 1.0, max: -2.0}));
+    /// assert!(Range::new(0.5, 0.5).is_err());
+    /// assert!(Range::new(f32::NAN, 1.0).is_err());
+    /// ```
+    pub fn new(min: T, max: T) -> Result<Range<T>, RangeError<T>>{
+        if min < max {
+            Ok(Range{min, max})
+        } else {
+            Err(RangeError{min, max})
+        }
+    }
+}
+
+impl<T: PartialOrd> Rect<T>{
+    /// Rect over `x_min..x_max` and `y_min..y_max`, both checked by `Range::new`.
+    pub fn from_bounds(x_min: T, x_max: T, y_min: T, y_max: T) -> Result<Rect<T>, RangeError<T>>{
+        Ok(Rect{x: Range::new(x_min, x_max)?, y: Range::new(y_min, y_max)?})
+    }
+}
+
+/// How `Rect::fit_to` reconciles a rect with a resolution of a different shape.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fit{
+    /// Widens the short axis, keeping the whole rect visible.
+    Expand,
+    /// Narrows the long axis, filling the image with part of the rect.
+    Crop,
+}
+
+/// Why a rect cannot be rendered.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RectError{
+    /// A bound is infinite or NaN.
+    NonFinite{axis: &'static str},
+    /// `min` is not smaller than `max`, leaving no area to map pixels onto.
+    Empty{axis: &'static str, min: f32, max: f32},
+}
+
+impl fmt::Display for RectError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            RectError::NonFinite{axis} => write!(f, "{axis} range has a bound that is not a finite number"),
+            RectError::Empty{axis, min, max} =>
+                write!(f, "{axis} range is empty: min ({min}) must be smaller than max ({max})"),
+        }
+    }
+}
+
+impl Error for RectError{}
+
+impl Range<f32>{
+    pub fn span(&self) -> f32{
+        self.max - self.min
+    }
+
+    fn resized(&self, span: f32) -> Range<f32>{
+        let center = (self.min + self.max) / 2.0;
+        Range{min: center - span / 2.0, max: center + span / 2.0}
+    }
+}
+
+impl Rect<f32>{
+    /// Checks that both ranges are finite and non-empty, so every pixel maps to a distinct point.
+    pub fn validate(&self) -> Result<(), RectError>{
+        for (axis, range) in [("x", &self.x), ("y", &self.y)] {
+            if !range.min.is_finite() || !range.max.is_finite() {
+                return Err(RectError::NonFinite{axis});
+            }
+            Range::new(range.min, range.max).map_err(|RangeError{min, max}| RectError::Empty{axis, min, max})?;
+        }
+        Ok(())
+    }
+
+    /// Tile `(x, y)` at zoom level `z` of the slippy-map scheme, which splits `world` into `2^z` tiles
+    /// per axis with `y` counted down from the top. Neighbouring tiles share their edges exactly. `None`
+    /// if `x` or `y` is not below `2^z`, the tile being off the map.
+    ///
+    /// ```
+    /// use mandelbrot::geometry::Rect;
+    ///
+    /// let world = Rect::from_bounds(-2.0, 1.0, -1.5, 1.5).unwrap();
+    /// assert_eq!(Rect::from_tile(0, 0, 0, &world), Some(world));
+    ///
+    /// let top_left = Rect::from_tile(1, 0, 0, &world).unwrap();
+    /// let bottom_right = Rect::from_tile(1, 1, 1, &world).unwrap();
+    /// assert_eq!((top_left.x.min, top_left.y.max), (world.x.min, world.y.max));
+    /// assert_eq!((bottom_right.x.max, bottom_right.y.min), (world.x.max, world.y.min));
+    /// assert_eq!(top_left.x.max, bottom_right.x.min);
+    /// assert_eq!(top_left.y.min, bottom_right.y.max);
+    /// assert_eq!(Rect::from_tile(1, 1, 0, &world).unwrap().x, bottom_right.x);
+    /// assert_eq!(Rect::from_tile(1, 0, 1, &world).unwrap().y, bottom_right.y);
+    ///
+    /// assert_eq!(Rect::from_tile(1, 2, 0, &world), None);
+    /// assert_eq!(Rect::from_tile(1, 0, 2, &world), None);
+    /// assert_eq!(Rect::from_tile(0, 0, 1, &world), None);
+    /// ```
+    pub fn from_tile(z: u32, x: u32, y: u32, world: &Rect<f32>) -> Option<Rect<f32>>{
+        let tiles = 1_u64 << z.min(63);
+        if x as u64 >= tiles || y as u64 >= tiles {
+            return None;
+        }
+        let edge = |range: &Range<f32>, index: u64| {
+            if index == 0 {
+                range.min
+            } else if index >= tiles {
+                range.max
+            } else {
+                make_lerp((0.0, tiles as f64), (range.min as f64, range.max as f64))(index as f64) as f32
+            }
+        };
+        // Rows count down from the top, while the imaginary axis grows upwards.
+        let (x, row) = (x as u64, tiles - (y as u64 + 1));
+        Some(Rect{
+            x: Range{min: edge(&world.x, x), max: edge(&world.x, x + 1)},
+            y: Range{min: edge(&world.y, row), max: edge(&world.y, row + 1)},
+        })
+    }
+
+    /// Extent of the shorter side of the view at zoom 1, which shows the whole Mandelbrot set.
+    pub const FULL_VIEW_EXTENT: f32 = 3.0;
+
+    /// Region centered on `center`, where each doubling of `zoom` halves the visible extent. The
+    /// longer side of `resolution` gets the proportionally longer range, so pixels stay square.
+    ///
+    /// ```
+    /// use mandelbrot::geometry::{Rect, Resolution};
+    /// use num::Complex;
+    ///
+    /// let center = Complex{re: -0.5, im: 0.0};
+    /// let square = Resolution{width: 512, height: 512};
+    /// assert_eq!(Rect::from_center_zoom(center, 1.0, &square), Rect::from_bounds(-2.0, 1.0, -1.5, 1.5).unwrap());
+    ///
+    /// let wide = Resolution{width: 1600, height: 900};
+    /// let (near, far) = (Rect::from_center_zoom(center, 4.0, &wide), Rect::from_center_zoom(center, 8.0, &wide));
+    /// assert_eq!((far.x.span() * 2.0, far.y.span() * 2.0), (near.x.span(), near.y.span()));
+    /// assert_eq!(far.x.min + far.x.max, center.re * 2.0);
+    /// ```
+    pub fn from_center_zoom(center: Complex<f32>, zoom: f32, resolution: &Resolution) -> Rect<f32>{
+        let extent = Self::FULL_VIEW_EXTENT / zoom;
+        let (width, height) = (resolution.width as f32, resolution.height as f32);
+        let (half_x, half_y) = if width >= height {
+            (extent * width / height / 2.0, extent / 2.0)
+        } else {
+            (extent / 2.0, extent * height / width / 2.0)
+        };
+
+        Rect{
+            x: Range{min: center.re - half_x, max: center.re + half_x},
+            y: Range{min: center.im - half_y, max: center.im + half_y},
+        }
+    }
+
+    /// Adjusts one axis around its center so the rect has the aspect ratio of `resolution`, keeping
+    /// circles circular.
+    ///
+    /// ```
+    /// use mandelbrot::geometry::{Fit, Rect, Resolution};
+    ///
+    /// let classic = Rect::from_bounds(-2.0, 1.0, -1.5, 1.5).unwrap();
+    /// let resolution = Resolution{width: 1600, height: 900};
+    /// let pixel = |rect: Rect<f32>| (rect.x.span() / 1600.0, rect.y.span() / 900.0);
+    ///
+    /// let expanded = classic.fit_to(&resolution, Fit::Expand);
+    /// let (width, height) = pixel(expanded);
+    /// assert!((width - height).abs() < height * 1e-6);
+    /// assert_eq!(expanded.y, classic.y);
+    /// assert!(expanded.x.min < classic.x.min && expanded.x.max > classic.x.max);
+    ///
+    /// let cropped = classic.fit_to(&resolution, Fit::Crop);
+    /// let (width, height) = pixel(cropped);
+    /// assert!((width - height).abs() < height * 1e-6);
+    /// assert_eq!(cropped.x, classic.x);
+    /// assert!(cropped.y.min > classic.y.min && cropped.y.max < classic.y.max);
+    /// ```
+    pub fn fit_to(&self, resolution: &Resolution, fit: Fit) -> Rect<f32>{
+        let target = resolution.width as f32 / resolution.height as f32;
+        let current = self.x.span() / self.y.span();
+
+        let resize_x = match fit {
+            Fit::Expand => current < target,
+            Fit::Crop => current > target,
+        };
+
+        if resize_x {
+            Rect{x: self.x.resized(self.y.span() * target), y: self.y}
+        } else {
+            Rect{x: self.x, y: self.y.resized(self.x.span() / target)}
+        }
+    }
+}
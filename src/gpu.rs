@@ -0,0 +1,257 @@
+//! Escape times computed in a wgpu compute shader, for views too slow to iterate on the CPU. Only the
+//! quadratic Mandelbrot set with the default `EscapeParams` runs on the GPU, in f32.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::mpsc;
+
+use log::warn;
+use wgpu::util::DeviceExt;
+
+use crate::compute::{make_calculations, Convergence, EscapeLimit, EscapeParams, Precision};
+use crate::geometry::{Rect, RectError, Resolution};
+
+/// Side of the square workgroups the shader runs in.
+const WORKGROUP_SIZE: u32 = 8;
+
+/// What the shader writes for points that never escape.
+const INSIDE: u32 = u32::MAX;
+
+const SHADER: &str = r#"
+struct View {
+    width:  u32,
+    height: u32,
+    limit:  u32,
+    // Negative without the convergence shortcut.
+    convergence_epsilon: f32,
+    x_min:  f32,
+    x_max:  f32,
+    y_min:  f32,
+    y_max:  f32,
+}
+
+@group(0) @binding(0) var<uniform> view: View;
+@group(0) @binding(1) var<storage, read_write> escape_times: array<u32>;
+
+fn in_cardioid_or_bulb(c: vec2<f32>) -> bool {
+    let im_sqr = c.y * c.y;
+    let x = c.x - 0.25;
+    let q = x * x + im_sqr;
+    let bulb_x = c.x + 1.0;
+    return q * (q + x) <= 0.25 * im_sqr || bulb_x * bulb_x + im_sqr <= 1.0 / 16.0;
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= view.width || id.y >= view.height {
+        return;
+    }
+    // Same mapping as `coordinate_mapper` in the default orientation, row 0 at the top.
+    let c = vec2<f32>(
+        view.x_min + f32(id.x) * (view.x_max - view.x_min) / f32(view.width),
+        view.y_max - f32(id.y) * (view.y_max - view.y_min) / f32(view.height),
+    );
+    let index = id.y * view.width + id.x;
+    if in_cardioid_or_bulb(c) {
+        escape_times[index] = 0xffffffffu;
+        return;
+    }
+
+    var z = vec2<f32>(0.0, 0.0);
+    for (var i = 0u; i < view.limit; i++) {
+        let norm_sqr = dot(z, z);
+        if norm_sqr > 4.0 {
+            escape_times[index] = i;
+            return;
+        }
+        if i > 0u && norm_sqr <= view.convergence_epsilon {
+            break;
+        }
+        z = vec2<f32>(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+    }
+    escape_times[index] = 0xffffffffu;
+}
+"#;
+
+#[derive(Debug)]
+pub enum GpuError{
+    NoAdapter(wgpu::RequestAdapterError),
+    Device(wgpu::RequestDeviceError),
+    /// The escape times of this many pixels do not fit in one buffer on this device.
+    TooLarge{pixels: u64},
+    Readback(wgpu::BufferAsyncError),
+    Poll(wgpu::PollError),
+}
+
+impl fmt::Display for GpuError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            GpuError::NoAdapter(err) => write!(f, "no GPU adapter: {err}"),
+            GpuError::Device(err) => write!(f, "could not open the GPU: {err}"),
+            GpuError::TooLarge{pixels} => write!(f, "{pixels} pixels are too many for one GPU buffer"),
+            GpuError::Readback(err) => write!(f, "could not read the escape times back: {err}"),
+            GpuError::Poll(err) => write!(f, "GPU failed while rendering: {err}"),
+        }
+    }
+}
+
+impl Error for GpuError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            GpuError::NoAdapter(err) => Some(err),
+            GpuError::Device(err) => Some(err),
+            GpuError::TooLarge{..} => None,
+            GpuError::Readback(err) => Some(err),
+            GpuError::Poll(err) => Some(err),
+        }
+    }
+}
+
+/// An open GPU with the escape time shader compiled, to render any number of views with.
+pub struct GpuRenderer{
+    device:   wgpu::Device,
+    queue:    wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl GpuRenderer{
+    /// Opens the default adapter, failing if the machine has none.
+    pub fn new() -> Result<GpuRenderer, GpuError>{
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+            .map_err(GpuError::NoAdapter)?;
+        let descriptor = wgpu::DeviceDescriptor{
+            label: Some("mandelbrot"),
+            required_limits: adapter.limits(),
+            ..Default::default()
+        };
+        let (device, queue) = pollster::block_on(adapter.request_device(&descriptor)).map_err(GpuError::Device)?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor{
+            label:  Some("escape times"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor{
+            label:               Some("escape times"),
+            layout:              None,
+            module:              &module,
+            entry_point:         Some("main"),
+            compilation_options: Default::default(),
+            cache:               None,
+        });
+        Ok(GpuRenderer{device, queue, pipeline})
+    }
+
+    /// Escape time of every pixel of `rect` in raster order, as `make_calculations` computes them in
+    /// `Single` precision up to rounding in the shader.
+    pub fn escape_times(&self, resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit) -> Result<Vec<Option<EscapeLimit>>, GpuError>{
+        let pixels = resolution.width as u64 * resolution.height as u64;
+        let size = pixels * 4;
+        let limits = self.device.limits();
+        if size > limits.max_storage_buffer_binding_size || size > limits.max_buffer_size {
+            return Err(GpuError::TooLarge{pixels});
+        }
+        if pixels == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut view = Vec::with_capacity(32);
+        for word in [resolution.width, resolution.height, limit as u32] {
+            view.extend_from_slice(&word.to_le_bytes());
+        }
+        view.extend_from_slice(&Convergence::Auto.epsilon(limit).unwrap_or(-1.0).to_le_bytes());
+        for bound in [rect.x.min, rect.x.max, rect.y.min, rect.y.max] {
+            view.extend_from_slice(&bound.to_le_bytes());
+        }
+        let uniforms = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+            label:    Some("view"),
+            contents: &view,
+            usage:    wgpu::BufferUsages::UNIFORM,
+        });
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor{
+            label:              Some("escape times"),
+            size,
+            usage:              wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor{
+            label:              Some("escape times readback"),
+            size,
+            usage:              wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor{
+            label:   Some("escape times"),
+            layout:  &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry{binding: 0, resource: uniforms.as_entire_binding()},
+                wgpu::BindGroupEntry{binding: 1, resource: output.as_entire_binding()},
+            ],
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor{label: Some("escape times")});
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor{label: Some("escape times"), timestamp_writes: None});
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(resolution.width.div_ceil(WORKGROUP_SIZE), resolution.height.div_ceil(WORKGROUP_SIZE), 1);
+        }
+        encoder.copy_buffer_to_buffer(&output, 0, &readback, 0, size);
+        self.queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = mpsc::channel();
+        readback.slice(..).map_async(wgpu::MapMode::Read, move |mapped| { let _ = sender.send(mapped); });
+        self.device.poll(wgpu::PollType::wait_indefinitely()).map_err(GpuError::Poll)?;
+        receiver.recv().expect("the map callback runs before the poll returns").map_err(GpuError::Readback)?;
+
+        let escape_times = readback.slice(..).get_mapped_range()
+            .expect("the readback buffer was just mapped")
+            .chunks_exact(4)
+            .map(|word| match u32::from_le_bytes([word[0], word[1], word[2], word[3]]) {
+                INSIDE => None,
+                escape_time => Some(escape_time as EscapeLimit),
+            })
+            .collect();
+        readback.unmap();
+        Ok(escape_times)
+    }
+}
+
+/// Escape times from whichever of the GPU or CPU computed them.
+pub type Calculations = Box<dyn Iterator<Item = ((u32, u32), Option<EscapeLimit>)>>;
+
+/// `make_calculations` with the default `EscapeParams` in `Single` precision, computed on `gpu` when
+/// given and able to, and on the CPU otherwise, with a warning logged if the GPU failed.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::gpu::{make_calculations_gpu, GpuRenderer};
+///
+/// let resolution = Resolution{width: 128, height: 96};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.2, 1.1).unwrap();
+/// let cpu: Vec<_> = make_calculations(resolution, rect, 200, Precision::Single, EscapeParams::default()).unwrap().collect();
+///
+/// // Without an adapter this compares the CPU fallback with itself.
+/// let gpu = GpuRenderer::new().ok();
+/// let computed: Vec<_> = make_calculations_gpu(gpu.as_ref(), resolution, rect, 200).unwrap().collect();
+/// assert_eq!(computed.len(), cpu.len());
+/// let differing = computed.iter().zip(&cpu).filter(|(a, b)| a != b).count();
+/// // f32 rounding in the shader may tip a few boundary pixels either way.
+/// assert!(differing * 100 <= cpu.len(), "{differing} pixels differ");
+/// ```
+pub fn make_calculations_gpu(gpu: Option<&GpuRenderer>, resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit)
+                     -> Result<Calculations, RectError> {
+    rect.validate()?;
+    let escape_times = gpu.and_then(|gpu| {
+        gpu.escape_times(resolution, rect, limit)
+            .inspect_err(|err| warn!("{err}, rendering on the CPU instead"))
+            .ok()
+    });
+    if let Some(escape_times) = escape_times {
+        let width = resolution.width.max(1);
+        let positions = (0..).map(move |index: u32| (index % width, index / width));
+        return Ok(Box::new(positions.zip(escape_times)));
+    }
+    Ok(Box::new(make_calculations(resolution, rect, limit, Precision::Single, EscapeParams::default())?))
+}
@@ -0,0 +1,254 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use image::{ImageError, Rgba, Rgba32FImage};
+
+use crate::color::{pixel_index, WriteError};
+use crate::compute::EscapeLimit;
+use crate::geometry::Resolution;
+
+/// Raw per-pixel escape values in row-major order, kept around for colorings that need to look at the
+/// whole tile before deciding on any pixel. `None` marks pixels inside the set.
+pub struct EscapeGrid<T = EscapeLimit>{
+    pub resolution: Resolution,
+    pub data:       Box<[Option<T>]>,
+}
+
+impl<T: Copy> EscapeGrid<T>{
+    pub fn new(resolution: &Resolution) -> Self{
+        let data = vec![None; (resolution.width as usize) * (resolution.height as usize)];
+        EscapeGrid{resolution: *resolution, data: data.into_boxed_slice()}
+    }
+
+    /// Stages the output of one of the `make_calculations` family.
+    pub fn collect(resolution: &Resolution, data: impl Iterator<Item = ((u32, u32), Option<T>)>) -> Result<Self, WriteError>{
+        let mut grid = EscapeGrid::new(resolution);
+        for (position, value) in data {
+            let index = pixel_index(&grid.resolution, position)?;
+            grid.data[index] = value;
+        }
+        Ok(grid)
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<T>{
+        pixel_index(&self.resolution, (x, y)).ok().and_then(|index| self.data[index])
+    }
+
+    /// Every pixel with its position, in the same shape `write_data` consumes.
+    pub fn iter(&self) -> impl Iterator<Item = ((u32, u32), Option<T>)> + '_{
+        let width = self.resolution.width.max(1) as usize;
+        self.data
+            .iter()
+            .enumerate()
+            .map(move |(index, value)| (((index % width) as u32, (index / width) as u32), *value))
+    }
+}
+
+/// Value `save_raw` stores for pixels inside the set. Escape counts stay below the limit, which is at
+/// most `u16::MAX`, so no escaped pixel can take it.
+pub const RAW_INSIDE: u16 = u16::MAX;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Creates `path` and writes the `.npy` header of a C-ordered `(height, width)` array of `descr`.
+fn create_npy(path: impl AsRef<Path>, descr: &str, resolution: Resolution) -> io::Result<BufWriter<File>>{
+    let Resolution{width, height} = resolution;
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': ({height}, {width}), }}");
+    // Magic, version and header length take 10 bytes; the header is padded so the data starts aligned.
+    let padding = 63 - (NPY_MAGIC.len() + 4 + header.len()) % 64;
+    header.extend(std::iter::repeat_n(' ', padding));
+    header.push('\n');
+
+    let mut output = BufWriter::new(File::create(path)?);
+    output.write_all(NPY_MAGIC)?;
+    output.write_all(&[1, 0])?;
+    output.write_all(&(header.len() as u16).to_le_bytes())?;
+    output.write_all(header.as_bytes())?;
+    Ok(output)
+}
+
+/// Writes the escape counts as a NumPy `.npy` array of little-endian `u16`, shaped `(height, width)`,
+/// with `RAW_INSIDE` for pixels inside the set. `numpy.load` reads it directly.
+pub fn save_raw(grid: &EscapeGrid, path: impl AsRef<Path>) -> io::Result<()>{
+    let mut output = create_npy(path, "<u2", grid.resolution)?;
+    for value in grid.data.iter() {
+        output.write_all(&value.unwrap_or(RAW_INSIDE).to_le_bytes())?;
+    }
+    output.flush()
+}
+
+/// Value `normalized_values` gives pixels inside the set, outside the `[0, 1]` every escaped pixel
+/// maps into.
+pub const NORMALIZED_INSIDE: f32 = -1.0;
+
+/// Escape values divided by `limit` and clamped to `[0, 1]`, in row-major order, with
+/// `NORMALIZED_INSIDE` for pixels inside the set. Integer and smooth escape values both work.
+///
+/// ```
+/// use mandelbrot::compute::{make_smooth_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::grid::{normalized_values, EscapeGrid, NORMALIZED_INSIDE};
+///
+/// let resolution = Resolution{width: 41, height: 31};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.5, 1.5).unwrap();
+/// let smooth = make_smooth_calculations(resolution, rect, 100, Precision::Single, EscapeParams::default()).unwrap();
+/// let values = normalized_values(&EscapeGrid::collect(&resolution, smooth).unwrap(), 100);
+/// assert_eq!(values.len(), 41 * 31);
+/// assert!(values.iter().all(|&value| (0.0..=1.0).contains(&value) || value == NORMALIZED_INSIDE));
+/// // The pixel on -0.5 + 0i lies in the main cardioid.
+/// assert_eq!(values[15 * 41 + 20], NORMALIZED_INSIDE);
+/// ```
+pub fn normalized_values<T: Into<f32> + Copy>(grid: &EscapeGrid<T>, limit: EscapeLimit) -> Vec<f32>{
+    let limit = limit as f32;
+    grid.data
+        .iter()
+        .map(|value| value.map_or(NORMALIZED_INSIDE, |value| (value.into() / limit).clamp(0.0, 1.0)))
+        .collect()
+}
+
+/// Writes `normalized_values` as a NumPy `.npy` array of little-endian `f32`, shaped
+/// `(height, width)`, for coloring in another program.
+pub fn save_normalized<T: Into<f32> + Copy>(grid: &EscapeGrid<T>, limit: EscapeLimit, path: impl AsRef<Path>) -> io::Result<()>{
+    let mut output = create_npy(path, "<f4", grid.resolution)?;
+    for value in normalized_values(grid, limit) {
+        output.write_all(&value.to_le_bytes())?;
+    }
+    output.flush()
+}
+
+#[derive(Debug)]
+pub enum LoadRawError{
+    Io(io::Error),
+    /// Not an `.npy` file as `save_raw` writes them.
+    Format(&'static str),
+}
+
+impl fmt::Display for LoadRawError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            LoadRawError::Io(err) => write!(f, "could not read escape counts: {err}"),
+            LoadRawError::Format(reason) => write!(f, "invalid escape count file: {reason}"),
+        }
+    }
+}
+
+impl Error for LoadRawError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            LoadRawError::Io(err) => Some(err),
+            LoadRawError::Format(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadRawError{
+    fn from(err: io::Error) -> Self{
+        LoadRawError::Io(err)
+    }
+}
+
+/// Reads back a file written by `save_raw`.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::grid::{load_raw, save_raw, EscapeGrid, RAW_INSIDE};
+///
+/// let resolution = Resolution{width: 24, height: 16};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.0, 1.0).unwrap();
+/// let data = make_calculations(resolution, rect, 200, Precision::Single, EscapeParams::default()).unwrap();
+/// let grid = EscapeGrid::collect(&resolution, data).unwrap();
+/// assert!(grid.data.contains(&None) && grid.data.iter().any(Option::is_some));
+///
+/// let path = std::env::temp_dir().join("mandelbrot-raw-doctest.npy");
+/// save_raw(&grid, &path).unwrap();
+/// let bytes = std::fs::read(&path).unwrap();
+/// let loaded = load_raw(&path).unwrap();
+/// std::fs::remove_file(&path).unwrap();
+///
+/// let header = bytes.len() - 2 * grid.data.len();
+/// let inside = header + 2 * grid.data.iter().position(Option::is_none).unwrap();
+/// assert_eq!(bytes[inside..inside + 2], RAW_INSIDE.to_le_bytes());
+/// assert_eq!(loaded.resolution, resolution);
+/// assert_eq!(loaded.data, grid.data);
+/// ```
+pub fn load_raw(path: impl AsRef<Path>) -> Result<EscapeGrid, LoadRawError>{
+    let bytes = fs::read(path)?;
+    let rest = bytes.strip_prefix(NPY_MAGIC).ok_or(LoadRawError::Format("missing .npy magic"))?;
+    let (version, rest) = rest.split_at_checked(2).ok_or(LoadRawError::Format("truncated header"))?;
+    if version != [1, 0] {
+        return Err(LoadRawError::Format("unsupported .npy version"));
+    }
+    let (header_len, rest) = rest.split_at_checked(2).ok_or(LoadRawError::Format("truncated header"))?;
+    let header_len = u16::from_le_bytes([header_len[0], header_len[1]]) as usize;
+    let (header, data) = rest.split_at_checked(header_len).ok_or(LoadRawError::Format("truncated header"))?;
+    let header = std::str::from_utf8(header).map_err(|_| LoadRawError::Format("header is not text"))?;
+
+    if !header.contains("'descr': '<u2'") || !header.contains("'fortran_order': False") {
+        return Err(LoadRawError::Format("expected a C-ordered array of little-endian u16"));
+    }
+    let shape = header
+        .split_once("'shape': (")
+        .and_then(|(_, shape)| shape.split_once(')'))
+        .and_then(|(shape, _)| shape.split_once(','))
+        .and_then(|(height, width)| Some((height.trim().parse().ok()?, width.trim().parse().ok()?)))
+        .ok_or(LoadRawError::Format("expected a two dimensional shape"))?;
+    let (height, width): (u32, u32) = shape;
+
+    let resolution = Resolution{width, height};
+    if data.len() != (width as usize) * (height as usize) * 2 {
+        return Err(LoadRawError::Format("data does not match the shape"));
+    }
+    let mut grid = EscapeGrid::new(&resolution);
+    for (value, sample) in grid.data.iter_mut().zip(data.chunks_exact(2)) {
+        let count = u16::from_le_bytes([sample[0], sample[1]]);
+        *value = (count != RAW_INSIDE).then_some(count);
+    }
+    Ok(grid)
+}
+
+/// Writes smooth escape values into an OpenEXR image at full `f32` precision, for tone-mapping in
+/// other tools. Every color channel holds the value; pixels inside the set are black with zero alpha,
+/// the rest opaque.
+///
+/// ```
+/// use mandelbrot::compute::{make_smooth_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::grid::{load_exr, save_exr, EscapeGrid};
+///
+/// let resolution = Resolution{width: 32, height: 24};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.2, 1.2).unwrap();
+/// let smooth = make_smooth_calculations(resolution, rect, 200, Precision::Single, EscapeParams::default()).unwrap();
+/// let grid = EscapeGrid::collect(&resolution, smooth).unwrap();
+///
+/// let path = std::env::temp_dir().join("mandelbrot_doctest_smooth.exr");
+/// save_exr(&grid, &path).unwrap();
+/// let loaded = load_exr(&path).unwrap();
+/// std::fs::remove_file(&path).unwrap();
+/// assert_eq!(loaded.resolution, resolution);
+/// assert_eq!(loaded.data, grid.data);
+/// ```
+pub fn save_exr(grid: &EscapeGrid<f32>, path: impl AsRef<Path>) -> Result<(), ImageError>{
+    let Resolution{width, height} = grid.resolution;
+    let image = Rgba32FImage::from_fn(width, height, |x, y| match grid.get(x, y) {
+        Some(value) => Rgba([value, value, value, 1.0]),
+        None => Rgba([0.0, 0.0, 0.0, 0.0]),
+    });
+    image.save_with_format(path, image::ImageFormat::OpenExr)
+}
+
+/// Reads back the smooth escape values of a file written by `save_exr`, taking the first channel
+/// of every opaque pixel.
+pub fn load_exr(path: impl AsRef<Path>) -> Result<EscapeGrid<f32>, ImageError>{
+    let image = image::open(path)?.into_rgba32f();
+    let resolution = Resolution{width: image.width(), height: image.height()};
+    let mut grid = EscapeGrid::new(&resolution);
+    for (value, pixel) in grid.data.iter_mut().zip(image.pixels()) {
+        let Rgba([level, _, _, alpha]) = *pixel;
+        *value = (alpha > 0.0).then_some(level);
+    }
+    Ok(grid)
+}
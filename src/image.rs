@@ -0,0 +1,704 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Cursor, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+use ::image::codecs::bmp::BmpEncoder;
+use ::image::codecs::jpeg::JpegEncoder;
+use ::image::{ColorType, ImageEncoder, ImageError};
+use flate2::{Compress, Compression, FlushCompress, Status};
+use memmap2::MmapMut;
+use rayon::prelude::*;
+
+use crate::geometry::Resolution;
+use crate::metadata::RenderMetadata;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PixelFormat{
+    L8,
+    /// 16-bit grayscale, each sample stored big-endian as in PNG.
+    L16,
+    /// Grayscale with alpha. Pixels that are never written stay fully transparent.
+    La8,
+    Rgb8,
+    /// `Rgb8` with alpha, likewise transparent until written.
+    Rgba8,
+}
+
+impl PixelFormat{
+    pub fn bytes_per_pixel(&self) -> usize{
+        match self {
+            PixelFormat::L8 => 1,
+            PixelFormat::L16 | PixelFormat::La8 => 2,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgba8 => 4,
+        }
+    }
+
+    fn png_color_type(&self) -> png::ColorType{
+        match self {
+            PixelFormat::L8 | PixelFormat::L16 => png::ColorType::Grayscale,
+            PixelFormat::La8 => png::ColorType::GrayscaleAlpha,
+            PixelFormat::Rgb8 => png::ColorType::Rgb,
+            PixelFormat::Rgba8 => png::ColorType::Rgba,
+        }
+    }
+
+    fn png_bit_depth(&self) -> png::BitDepth{
+        match self {
+            PixelFormat::L16 => png::BitDepth::Sixteen,
+            _ => png::BitDepth::Eight,
+        }
+    }
+
+    pub fn color_type(&self) -> ColorType{
+        match self {
+            PixelFormat::L8 => ColorType::L8,
+            PixelFormat::L16 => ColorType::L16,
+            PixelFormat::La8 => ColorType::La8,
+            PixelFormat::Rgb8 => ColorType::Rgb8,
+            PixelFormat::Rgba8 => ColorType::Rgba8,
+        }
+    }
+}
+
+/// Pixel bytes of an `Image`, on the heap or in a memory-mapped file. Either way they read and write
+/// as a plain `[u8]`.
+pub enum ImageData{
+    Heap(Box<[u8]>),
+    /// Backed by a file, so images larger than memory only keep the pages in use resident.
+    Mapped(MmapMut),
+}
+
+impl Deref for ImageData{
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8]{
+        match self {
+            ImageData::Heap(data) => data,
+            ImageData::Mapped(map) => map,
+        }
+    }
+}
+
+impl DerefMut for ImageData{
+    fn deref_mut(&mut self) -> &mut [u8]{
+        match self {
+            ImageData::Heap(data) => data,
+            ImageData::Mapped(map) => map,
+        }
+    }
+}
+
+impl From<Box<[u8]>> for ImageData{
+    fn from(data: Box<[u8]>) -> Self{
+        ImageData::Heap(data)
+    }
+}
+
+impl PartialEq for ImageData{
+    fn eq(&self, other: &ImageData) -> bool{
+        self[..] == other[..]
+    }
+}
+
+impl fmt::Debug for ImageData{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            ImageData::Heap(data) => write!(f, "Heap({} bytes)", data.len()),
+            ImageData::Mapped(map) => write!(f, "Mapped({} bytes)", map.len()),
+        }
+    }
+}
+
+pub struct Image{
+    pub resolution: Resolution,
+    pub format:     PixelFormat,
+    pub data:       ImageData,
+}
+
+impl Image{
+    pub fn new(res: &Resolution) -> Self{
+        Image::with_format(res, PixelFormat::L8)
+    }
+
+    /// A blank image stored in a new file at `path`, mapped into memory, for resolutions too large to
+    /// keep on the heap. The file holds the raw pixel bytes and is left in place afterwards.
+    ///
+    /// ```
+    /// use mandelbrot::compute::{EscapeParams, Precision};
+    /// use mandelbrot::geometry::{Rect, Resolution};
+    /// use mandelbrot::image::{Image, PixelFormat};
+    /// use mandelbrot::render::render_parallel;
+    ///
+    /// let resolution = Resolution{width: 300, height: 200};
+    /// let rect = Rect::from_bounds(-2.0, 1.0, -1.0, 1.0).unwrap();
+    /// let params = EscapeParams::default();
+    ///
+    /// let path = std::env::temp_dir().join("mandelbrot-mapped-doctest.raw");
+    /// let mut mapped = Image::mapped(&resolution, PixelFormat::L8, &path).unwrap();
+    /// let mut heap = Image::new(&resolution);
+    /// render_parallel(&mut mapped, rect, 128, Precision::Single, params, 1.0).unwrap();
+    /// render_parallel(&mut heap, rect, 128, Precision::Single, params, 1.0).unwrap();
+    /// assert_eq!(mapped.data, heap.data);
+    /// # drop(mapped);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn mapped(res: &Resolution, format: PixelFormat, path: impl AsRef<Path>) -> io::Result<Self>{
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        file.set_len(res.width as u64 * res.height as u64 * format.bytes_per_pixel() as u64)?;
+        // SAFETY: the file was just created and truncated for this image. Nothing else in the process
+        // maps it, and writes to it by other processes while rendering are outside what we support.
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        Ok(Image{resolution: *res, format, data: ImageData::Mapped(map)})
+    }
+
+    pub fn with_format(res: &Resolution, format: PixelFormat) -> Self{
+        let data = vec![0; (res.width as usize) * (res.height as usize) * format.bytes_per_pixel()];
+
+        Image{
+            resolution: *res,
+            format,
+            data: data.into_boxed_slice().into(),
+        }
+    }
+
+    /// Copy `factor` times smaller along each side, each pixel the rounded mean of a `factor` by
+    /// `factor` box. Sides that do not divide evenly round up, and boxes reaching past the edge repeat
+    /// the last row or column.
+    ///
+    /// ```
+    /// use mandelbrot::geometry::Resolution;
+    /// use mandelbrot::image::Image;
+    ///
+    /// let mut image = Image::new(&Resolution{width: 4, height: 4});
+    /// image.data.copy_from_slice(&[
+    ///     0, 2, 10, 10,
+    ///     4, 6, 20, 30,
+    ///     1, 1, 255, 255,
+    ///     1, 2, 255, 254,
+    /// ]);
+    /// let half = image.downsampled(2);
+    /// assert_eq!(half.resolution, Resolution{width: 2, height: 2});
+    /// assert_eq!(&half.data[..], &[3, 18, 1, 255]);
+    /// ```
+    pub fn downsampled(&self, factor: u32) -> Image{
+        let factor = factor.max(1);
+        let Resolution{width, height} = self.resolution;
+        let resolution = Resolution{width: width.div_ceil(factor), height: height.div_ceil(factor)};
+        let mut output = Image::with_format(&resolution, self.format);
+
+        // 16-bit samples are averaged as big-endian pairs, everything else byte by byte.
+        let sample_bytes = if self.format == PixelFormat::L16 { 2 } else { 1 };
+        let samples_per_pixel = self.format.bytes_per_pixel() / sample_bytes;
+        let sample = |x: u32, y: u32, channel: usize| -> u32 {
+            let offset = ((y as usize * width as usize + x as usize) * samples_per_pixel + channel) * sample_bytes;
+            match sample_bytes {
+                2 => u16::from_be_bytes([self.data[offset], self.data[offset + 1]]) as u32,
+                _ => self.data[offset] as u32,
+            }
+        };
+
+        let count = factor * factor;
+        for out_y in 0..resolution.height {
+            for out_x in 0..resolution.width {
+                for channel in 0..samples_per_pixel {
+                    let mut sum = 0;
+                    for dy in 0..factor {
+                        for dx in 0..factor {
+                            let x = (out_x * factor + dx).min(width - 1);
+                            let y = (out_y * factor + dy).min(height - 1);
+                            sum += sample(x, y, channel);
+                        }
+                    }
+                    let mean = (sum + count / 2) / count;
+                    let offset = ((out_y as usize * resolution.width as usize + out_x as usize) * samples_per_pixel + channel) * sample_bytes;
+                    match sample_bytes {
+                        2 => output.data[offset..offset + 2].copy_from_slice(&(mean as u16).to_be_bytes()),
+                        _ => output.data[offset] = mean as u8,
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+/// Successively `factor` times smaller copies of `img`, down to a single pixel, for navigating an
+/// image too large to view whole. `img` itself is not included.
+pub fn mip_levels(img: &Image, factor: u32) -> Vec<Image>{
+    let mut levels: Vec<Image> = Vec::new();
+    let factor = factor.max(2);
+    loop {
+        let previous = levels.last().unwrap_or(img);
+        if previous.resolution.width <= 1 && previous.resolution.height <= 1 {
+            return levels;
+        }
+        let next = previous.downsampled(factor);
+        levels.push(next);
+    }
+}
+
+/// Path of mip level `level` of the image at `path`, e.g. `overview_mip2.png` for `overview.png`.
+pub fn mip_level_path(path: impl AsRef<Path>, level: usize) -> PathBuf{
+    let path = path.as_ref();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}_mip{level}.{}", extension.to_string_lossy()),
+        None => format!("{stem}_mip{level}"),
+    };
+    path.with_file_name(name)
+}
+
+
+/// Default `tile_is_interesting` threshold. Tiles whose pixels spread over no more than 20 of the
+/// 256 gray levels are practically uniform.
+pub const DEFAULT_MIN_CONTRAST: u8 = 20;
+
+/// Whether the pixel values spread by more than `threshold`. Empty tiles are never interesting.
+///
+/// ```
+/// use mandelbrot::image::{tile_is_interesting, DEFAULT_MIN_CONTRAST};
+///
+/// assert!(!tile_is_interesting(&[], DEFAULT_MIN_CONTRAST));
+/// assert!(!tile_is_interesting(&[128; 64], 0));
+/// assert!(!tile_is_interesting(&[10, 30, 20], 20));
+/// assert!(tile_is_interesting(&[10, 31, 20], 20));
+/// ```
+pub fn tile_is_interesting(data: &[u8], threshold: u8) -> bool{
+    match (data.iter().min(), data.iter().max()) {
+        (Some(min), Some(max)) => max - min > threshold,
+        _ => false,
+    }
+}
+
+/// Whether both the inside and outside of the set cover at least `min_fraction` of the tile, which
+/// means the tile straddles the set's boundary. A fraction exactly at `min_fraction` passes:
+///
+/// ```
+/// use mandelbrot::image::{tile_is_interesting, tile_is_mixed};
+///
+/// assert!(tile_is_mixed(25, 100, 0.25) && tile_is_mixed(75, 100, 0.25));
+/// assert!(!tile_is_mixed(24, 100, 0.25) && !tile_is_mixed(76, 100, 0.25));
+/// assert!(!tile_is_mixed(0, 0, 0.0));
+///
+/// // Likewise the contrast threshold: a spread equal to it is still too uniform.
+/// let tile = [100, 120, 110];
+/// assert!(!tile_is_interesting(&tile, 20) && tile_is_interesting(&tile, 19));
+/// ```
+pub fn tile_is_mixed(inside: usize, total: usize, min_fraction: f32) -> bool{
+    if total == 0 {
+        return false;
+    }
+    let minority = inside.min(total - inside);
+    minority as f32 / total as f32 >= min_fraction
+}
+
+pub const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+/// File format `save_image` encodes into. Only PNG keeps 16-bit samples and render metadata.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputFormat{
+    Png,
+    /// Lossy, with `quality` from 1 to 100.
+    Jpeg{quality: u8},
+    Bmp,
+}
+
+impl OutputFormat{
+    /// Guesses the format from the extension of `path`, `None` for unknown extensions.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<OutputFormat>{
+        let extension = path.as_ref().extension()?.to_str()?;
+        extension.to_ascii_lowercase().parse().ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOutputFormatError(pub String);
+
+impl fmt::Display for ParseOutputFormatError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "unknown output format `{}`", self.0)
+    }
+}
+
+impl Error for ParseOutputFormatError{}
+
+/// Accepts `png`, `jpeg` (or `jpg`, at `DEFAULT_JPEG_QUALITY`) and `bmp`.
+impl std::str::FromStr for OutputFormat{
+    type Err = ParseOutputFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>{
+        match s {
+            "png" => Ok(OutputFormat::Png),
+            "jpeg" | "jpg" => Ok(OutputFormat::Jpeg{quality: DEFAULT_JPEG_QUALITY}),
+            "bmp" => Ok(OutputFormat::Bmp),
+            _ => Err(ParseOutputFormatError(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError{
+    Io(io::Error),
+    Encoding(ImageError),
+    Png(png::EncodingError),
+    /// The pixel buffer does not hold `width * height` pixels of the image's format.
+    BufferSize{expected: usize, actual: usize},
+}
+
+impl fmt::Display for SaveError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            SaveError::Io(err) => write!(f, "could not write image: {err}"),
+            SaveError::Encoding(err) => write!(f, "could not encode image: {err}"),
+            SaveError::Png(err) => write!(f, "could not encode image: {err}"),
+            SaveError::BufferSize{expected, actual} =>
+                write!(f, "image buffer holds {actual} bytes but its resolution needs {expected}"),
+        }
+    }
+}
+
+impl Error for SaveError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            SaveError::Io(err) => Some(err),
+            SaveError::Encoding(err) => Some(err),
+            SaveError::Png(err) => Some(err),
+            SaveError::BufferSize{..} => None,
+        }
+    }
+}
+
+impl From<io::Error> for SaveError{
+    fn from(err: io::Error) -> Self{
+        SaveError::Io(err)
+    }
+}
+
+impl From<ImageError> for SaveError{
+    fn from(err: ImageError) -> Self{
+        SaveError::Encoding(err)
+    }
+}
+
+impl From<png::EncodingError> for SaveError{
+    fn from(err: png::EncodingError) -> Self{
+        SaveError::Png(err)
+    }
+}
+
+fn check_buffer_size(img: &Image) -> Result<(), SaveError>{
+    let expected = (img.resolution.width as usize) * (img.resolution.height as usize) * img.format.bytes_per_pixel();
+    if img.data.len() != expected {
+        return Err(SaveError::BufferSize{expected, actual: img.data.len()});
+    }
+    Ok(())
+}
+
+/// Encodes `img` in `format` into memory, returning the file's bytes. PNG goes through the parallel
+/// `encode_png`.
+///
+/// ```
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{encode_image, Image, OutputFormat};
+///
+/// let image = Image::new(&Resolution{width: 4, height: 3});
+/// let bytes = encode_image(&image, OutputFormat::Png).unwrap();
+/// let decoded = image::load_from_memory(&bytes).unwrap();
+/// assert_eq!((decoded.width(), decoded.height()), (4, 3));
+/// ```
+///
+/// Each format decodes back to the same pixels, JPEG only approximately:
+///
+/// ```
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{encode_image, Image, OutputFormat};
+///
+/// let mut image = Image::new(&Resolution{width: 16, height: 8});
+/// for (index, pixel) in image.data.iter_mut().enumerate() {
+///     *pixel = (index % 16 * 16) as u8;
+/// }
+/// for name in ["png", "jpeg", "bmp"] {
+///     let format: OutputFormat = name.parse().unwrap();
+///     assert_eq!(OutputFormat::from_path(format!("out.{name}")), Some(format));
+///     let bytes = encode_image(&image, format).unwrap();
+///     let decoded = image::load_from_memory(&bytes).unwrap().into_luma8();
+///     assert_eq!(decoded.dimensions(), (16, 8));
+///     let worst = decoded.as_raw().iter().zip(&image.data[..]).map(|(&a, &b)| a.abs_diff(b)).max().unwrap();
+///     match format {
+///         OutputFormat::Jpeg{..} => assert!(worst <= 8, "{name} is off by {worst}"),
+///         _ => assert_eq!(worst, 0, "{name} is off by {worst}"),
+///     }
+/// }
+/// ```
+pub fn encode_image(img: &Image, format: OutputFormat) -> Result<Vec<u8>, SaveError>{
+    let mut output = Cursor::new(Vec::new());
+    let (width, height, color_type) = (img.resolution.width, img.resolution.height, img.format.color_type().into());
+    match format {
+        OutputFormat::Png => return encode_png(img, None),
+        OutputFormat::Jpeg{quality} =>
+            JpegEncoder::new_with_quality(&mut output, quality.clamp(1, 100)).write_image(&native_samples(img)?, width, height, color_type)?,
+        OutputFormat::Bmp => BmpEncoder::new(&mut output).write_image(&native_samples(img)?, width, height, color_type)?,
+    }
+
+    Ok(output.into_inner())
+}
+
+/// The pixel bytes of `img` as `image`'s encoders take them, with 16-bit samples in native byte order.
+fn native_samples(img: &Image) -> Result<Cow<'_, [u8]>, SaveError>{
+    check_buffer_size(img)?;
+    Ok(match img.format {
+        PixelFormat::L16 => img.data
+            .chunks_exact(2)
+            .flat_map(|sample| u16::from_be_bytes([sample[0], sample[1]]).to_ne_bytes())
+            .collect(),
+        _ => Cow::Borrowed(&img.data[..]),
+    })
+}
+
+/// Encodes `img` with `encode_image` and writes it to `path`. A buffer that does not match the
+/// resolution is an error, and nothing is written:
+///
+/// ```
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{save_image, Image, OutputFormat, PixelFormat, SaveError};
+///
+/// let short = Image{
+///     resolution: Resolution{width: 4, height: 3},
+///     format: PixelFormat::L8,
+///     data: vec![0; 5].into_boxed_slice().into(),
+/// };
+/// let path = std::env::temp_dir().join("mandelbrot-save-image-doctest.png");
+/// let result = save_image(&short, &path, OutputFormat::Png);
+/// assert!(matches!(result, Err(SaveError::BufferSize{expected: 12, actual: 5})));
+/// assert!(!path.exists());
+/// ```
+pub fn save_image(img: &Image, path: impl AsRef<Path>, format: OutputFormat) -> Result<(), SaveError>{
+    let bytes = encode_image(img, format)?;
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Like `save_image`, but also records the render parameters as PNG `tEXt` chunks.
+pub fn save_image_with_metadata(img: &Image, path: impl AsRef<Path>, metadata: &RenderMetadata) -> Result<(), SaveError>{
+    let bytes = encode_png(img, Some(metadata))?;
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Writes `img` to `path` as PNG a band of rows at a time, so memory use stays flat however large the
+/// image is. Meant for `Image::mapped` images, whose pages are only read in as the rows go out.
+/// `metadata` goes into `tEXt` chunks as in `encode_png`.
+pub fn stream_png(img: &Image, path: impl AsRef<Path>, metadata: Option<&RenderMetadata>) -> Result<(), SaveError>{
+    check_buffer_size(img)?;
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, img.resolution.width, img.resolution.height);
+    encoder.set_color(img.format.png_color_type());
+    encoder.set_depth(img.format.png_bit_depth());
+    for (keyword, text) in metadata.map(RenderMetadata::to_text).unwrap_or_default() {
+        encoder.add_text_chunk(keyword, text)?;
+    }
+    let mut writer = encoder.write_header()?;
+    let mut stream = writer.stream_writer()?;
+    let row_bytes = (img.resolution.width as usize * img.format.bytes_per_pixel()).max(1);
+    for band in img.data.chunks((PNG_SEGMENT_BYTES / row_bytes).max(1) * row_bytes) {
+        stream.write_all(band)?;
+    }
+    stream.finish()?;
+    Ok(())
+}
+
+/// Filtered bytes each thread of `encode_png` compresses on its own.
+const PNG_SEGMENT_BYTES: usize = 1 << 20;
+const IDAT_CHUNK_BYTES: usize = 1 << 20;
+
+/// Applies the PNG Paeth filter to every row in parallel, prefixing each with its filter type.
+fn paeth_filtered(data: &[u8], row_bytes: usize, bytes_per_pixel: usize) -> Vec<u8>{
+    let mut filtered = vec![0; data.len() + data.len() / row_bytes.max(1)];
+    filtered
+        .par_chunks_mut(row_bytes + 1)
+        .enumerate()
+        .for_each(|(y, out)| {
+            let row = &data[y * row_bytes..(y + 1) * row_bytes];
+            let above = if y > 0 { Some(&data[(y - 1) * row_bytes..y * row_bytes]) } else { None };
+            out[0] = 4;
+            for i in 0..row_bytes {
+                let left = if i >= bytes_per_pixel { row[i - bytes_per_pixel] } else { 0 };
+                let (up, up_left) = match above {
+                    Some(above) => (above[i], if i >= bytes_per_pixel { above[i - bytes_per_pixel] } else { 0 }),
+                    None => (0, 0),
+                };
+                let estimate = left as i16 + up as i16 - up_left as i16;
+                let (to_left, to_up, to_up_left) =
+                    ((estimate - left as i16).abs(), (estimate - up as i16).abs(), (estimate - up_left as i16).abs());
+                let predictor = if to_left <= to_up && to_left <= to_up_left {
+                    left
+                } else if to_up <= to_up_left {
+                    up
+                } else {
+                    up_left
+                };
+                out[i + 1] = row[i].wrapping_sub(predictor);
+            }
+        });
+    filtered
+}
+
+/// Raw DEFLATE of `data`, ending byte-aligned with a sync flush so the next segment can follow, or
+/// with the final block.
+fn deflate_segment(data: &[u8], last: bool) -> Result<Vec<u8>, SaveError>{
+    let flush = if last { FlushCompress::Finish } else { FlushCompress::Sync };
+    let mut compress = Compress::new(Compression::default(), false);
+    let mut out = Vec::with_capacity(data.len() / 4 + 64);
+    loop {
+        let consumed = compress.total_in() as usize;
+        let status = compress.compress_vec(&data[consumed..], &mut out, flush).map_err(io::Error::other)?;
+        let done = compress.total_in() as usize == data.len()
+            && if last { status == Status::StreamEnd } else { out.len() < out.capacity() };
+        if done {
+            return Ok(out);
+        }
+        out.reserve(out.capacity().max(64));
+    }
+}
+
+fn adler32(data: &[u8]) -> u32{
+    const MODULUS: u32 = 65521;
+    // Largest run whose sums cannot overflow before reducing.
+    const RUN: usize = 5552;
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for run in data.chunks(RUN) {
+        for &byte in run {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MODULUS;
+        b %= MODULUS;
+    }
+    (b << 16) | a
+}
+
+/// Encodes `img` as PNG at zlib's default level, with `metadata` in `tEXt` chunks if given. The rows
+/// are filtered and compressed in parallel segments joined into one zlib stream, so large images do
+/// not wait on a single compressor thread. Small images fit in one segment and gain nothing.
+///
+/// ```
+/// use mandelbrot::geometry::Resolution;
+/// use mandelbrot::image::{encode_png, Image, PixelFormat};
+///
+/// // Odd widths over several 1 MiB segments, so segments end partway through the image.
+/// for (width, height, format) in [(1237, 900, PixelFormat::Rgb8), (333, 1700, PixelFormat::L16), (1, 1, PixelFormat::L8)] {
+///     let mut image = Image::with_format(&Resolution{width, height}, format);
+///     for (index, byte) in image.data.iter_mut().enumerate() {
+///         *byte = (index.wrapping_mul(2654435761) >> 13) as u8 ^ (index / 4096) as u8;
+///     }
+///     let bytes = encode_png(&image, None).unwrap();
+///
+///     let mut reader = png::Decoder::new(std::io::Cursor::new(bytes)).read_info().unwrap();
+///     let mut decoded = vec![0; reader.output_buffer_size().unwrap()];
+///     let info = reader.next_frame(&mut decoded).unwrap();
+///     assert_eq!((info.width, info.height), (width, height));
+///     assert_eq!(&decoded[..info.buffer_size()], &image.data[..]);
+/// }
+/// ```
+pub fn encode_png(img: &Image, metadata: Option<&RenderMetadata>) -> Result<Vec<u8>, SaveError>{
+    check_buffer_size(img)?;
+
+    let bytes_per_pixel = img.format.bytes_per_pixel();
+    let row_bytes = img.resolution.width as usize * bytes_per_pixel;
+    let filtered = paeth_filtered(&img.data, row_bytes, bytes_per_pixel);
+
+    let segment_rows = (PNG_SEGMENT_BYTES / (row_bytes + 1)).max(1);
+    let segments: Vec<&[u8]> = filtered.chunks(segment_rows * (row_bytes + 1)).collect();
+    let last = segments.len().saturating_sub(1);
+    let compressed = if segments.is_empty() {
+        vec![deflate_segment(&[], true)?]
+    } else {
+        segments
+            .par_iter()
+            .enumerate()
+            .map(|(index, segment)| deflate_segment(segment, index == last))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    // zlib header for a 32 KiB window at the default level, then the segments and the checksum.
+    let mut stream = Vec::with_capacity(compressed.iter().map(Vec::len).sum::<usize>() + 6);
+    stream.extend_from_slice(&[0x78, 0x9c]);
+    for segment in &compressed {
+        stream.extend_from_slice(segment);
+    }
+    stream.extend_from_slice(&adler32(&filtered).to_be_bytes());
+
+    let mut output = Vec::with_capacity(stream.len() + 1024);
+    let mut encoder = png::Encoder::new(&mut output, img.resolution.width, img.resolution.height);
+    encoder.set_color(img.format.png_color_type());
+    encoder.set_depth(img.format.png_bit_depth());
+    for (keyword, text) in metadata.map(RenderMetadata::to_text).unwrap_or_default() {
+        encoder.add_text_chunk(keyword, text)?;
+    }
+    let mut writer = encoder.write_header()?;
+    for chunk in stream.chunks(IDAT_CHUNK_BYTES) {
+        writer.write_chunk(png::chunk::IDAT, chunk)?;
+    }
+    writer.finish()?;
+
+    Ok(output)
+}
+
+#[derive(Debug)]
+pub enum OutputDirError{
+    /// Something other than a directory is already there.
+    NotADirectory(PathBuf),
+    Create{path: PathBuf, source: io::Error},
+    /// The directory exists, but a file could not be created in it.
+    NotWritable{path: PathBuf, source: io::Error},
+}
+
+impl fmt::Display for OutputDirError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            OutputDirError::NotADirectory(path) => write!(f, "{} exists and is not a directory", path.display()),
+            OutputDirError::Create{path, source} => write!(f, "could not create {}: {source}", path.display()),
+            OutputDirError::NotWritable{path, source} => write!(f, "cannot write into {}: {source}", path.display()),
+        }
+    }
+}
+
+impl Error for OutputDirError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            OutputDirError::NotADirectory(_) => None,
+            OutputDirError::Create{source, ..} => Some(source),
+            OutputDirError::NotWritable{source, ..} => Some(source),
+        }
+    }
+}
+
+/// Creates `dir` and its parents if needed, then checks that files can be written into it by creating
+/// and removing a probe file, so a read-only destination fails before a long render instead of after.
+///
+/// ```
+/// use mandelbrot::image::{prepare_output_dir, OutputDirError};
+///
+/// let file = std::env::temp_dir().join("mandelbrot-output-dir-doctest");
+/// std::fs::write(&file, b"").unwrap();
+/// assert!(matches!(prepare_output_dir(&file), Err(OutputDirError::NotADirectory(_))));
+/// # std::fs::remove_file(&file).unwrap();
+/// ```
+pub fn prepare_output_dir(dir: impl AsRef<Path>) -> Result<(), OutputDirError>{
+    let dir = dir.as_ref();
+    if dir.exists() && !dir.is_dir() {
+        return Err(OutputDirError::NotADirectory(dir.to_path_buf()));
+    }
+    fs::create_dir_all(dir).map_err(|source| OutputDirError::Create{path: dir.to_path_buf(), source})?;
+
+    let probe = dir.join(format!(".mandelbrot-write-test-{}", std::process::id()));
+    OpenOptions::new().write(true).create_new(true).open(&probe)
+        .map_err(|source| OutputDirError::NotWritable{path: dir.to_path_buf(), source})?;
+    fs::remove_file(&probe).map_err(|source| OutputDirError::NotWritable{path: dir.to_path_buf(), source})
+}
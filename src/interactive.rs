@@ -0,0 +1,111 @@
+//! A window to explore the set in: clicking recenters the view and the scroll wheel zooms. Every change
+//! of view first shows a coarse preview, then refines it through `ProgressiveRender` while the view
+//! stays put.
+
+use std::time::Duration;
+
+use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
+use num::Complex;
+
+use crate::color::GrayLut;
+use crate::compute::{coordinate_mapper, EscapeLimit, EscapeParams, Precision};
+use crate::geometry::{Fit, Rect, Resolution};
+use crate::grid::EscapeGrid;
+use crate::render::ProgressiveRender;
+
+/// Largest window `explore` opens, `resolution` being scaled down to fit it otherwise.
+pub const MAX_WINDOW: Resolution = Resolution{width: 1024, height: 768};
+
+/// Step of the first pass after the view changes.
+const PREVIEW_STEP: u32 = 8;
+
+/// Zoom factor of one notch of the scroll wheel.
+const ZOOM_PER_NOTCH: f32 = 1.25;
+
+const IDLE_FRAME: Duration = Duration::from_millis(16);
+
+fn fit_window(resolution: Resolution) -> Resolution{
+    let scale = (MAX_WINDOW.width as f32 / resolution.width as f32)
+        .min(MAX_WINDOW.height as f32 / resolution.height as f32)
+        .min(1.0);
+    Resolution{
+        width:  ((resolution.width as f32 * scale) as u32).max(1),
+        height: ((resolution.height as f32 * scale) as u32).max(1),
+    }
+}
+
+fn gray_buffer(grid: &EscapeGrid, lut: &GrayLut) -> Vec<u32>{
+    grid.data.iter()
+        .map(|&escape_time| {
+            let level = lut.level(escape_time) as u32;
+            level << 16 | level << 8 | level
+        })
+        .collect()
+}
+
+/// What the user asked of the view since the last frame.
+enum ViewInput{
+    Recenter{x: f32, y: f32},
+    Zoom(f32),
+}
+
+fn view_input(window: &Window) -> Option<ViewInput>{
+    if let Some((_, notches)) = window.get_scroll_wheel() && notches != 0.0 {
+        return Some(ViewInput::Zoom(ZOOM_PER_NOTCH.powf(notches.signum())));
+    }
+    if window.get_mouse_down(MouseButton::Left) {
+        return window.get_mouse_pos(MouseMode::Discard).map(|(x, y)| ViewInput::Recenter{x, y});
+    }
+    None
+}
+
+/// Opens a window onto `rect`, widened to the aspect ratio of `resolution`, and lets the user click and
+/// scroll around until they close it or press Escape. Returns the view it was left on.
+pub fn explore(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit) -> Result<Rect<f32>, minifb::Error>{
+    let resolution = fit_window(resolution);
+    let mut window = Window::new("mandelbrot", resolution.width as usize, resolution.height as usize, WindowOptions::default())?;
+    // Refining paces the frames itself, so only idle ones sleep.
+    window.set_target_fps(0);
+
+    let rect = rect.fit_to(&resolution, Fit::Expand);
+    let mut center = Complex{re: (rect.x.min + rect.x.max) / 2.0, im: (rect.y.min + rect.y.max) / 2.0};
+    let mut zoom = Rect::FULL_VIEW_EXTENT / rect.x.span().min(rect.y.span());
+    let mut view = Rect::from_center_zoom(center, zoom, &resolution);
+    let lut = GrayLut::new(limit, 1.0);
+    let new_render = |view| ProgressiveRender::new(resolution, view, limit, Precision::for_rect(&view, &resolution), EscapeParams::default(), PREVIEW_STEP)
+        .expect("explore keeps the view valid");
+    let mut progressive = new_render(view);
+    // One recenter per click rather than one per frame the button is held.
+    let mut was_down = false;
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let (mut next_center, mut next_zoom) = (center, zoom);
+        let is_down = window.get_mouse_down(MouseButton::Left);
+        match view_input(&window) {
+            Some(ViewInput::Zoom(factor)) => next_zoom *= factor,
+            Some(ViewInput::Recenter{x, y}) if !was_down => {
+                let mapper = coordinate_mapper(resolution, view, Precision::Double, EscapeParams::default().orientation);
+                let c = mapper(x as f64, y as f64);
+                next_center = Complex{re: c.re as f32, im: c.im as f32};
+            }
+            _ => {}
+        }
+        was_down = is_down;
+
+        let next = Rect::from_center_zoom(next_center, next_zoom, &resolution);
+        // Past the depth f32 bounds can tell apart, the view stays where it was.
+        if next != view && next.validate().is_ok() {
+            (center, zoom, view) = (next_center, next_zoom, next);
+            progressive = new_render(view);
+        }
+
+        if progressive.refine().is_some() {
+            let buffer = gray_buffer(progressive.grid(), &lut);
+            window.update_with_buffer(&buffer, resolution.width as usize, resolution.height as usize)?;
+        } else {
+            window.update();
+            std::thread::sleep(IDLE_FRAME);
+        }
+    }
+    Ok(view)
+}
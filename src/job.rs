@@ -0,0 +1,218 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use num::Complex;
+use serde::Deserialize;
+
+use crate::color::{Gradient, LoadPaletteError};
+use crate::compute::EscapeLimit;
+use crate::geometry::{Rect, RectError, Resolution};
+
+/// Region a job renders, either as explicit bounds or as a center and zoom.
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum JobView{
+    Rect{rect: Rect<f32>},
+    /// `zoom` as in `Rect::from_center_zoom`.
+    Center{center: (f32, f32), zoom: f32},
+}
+
+fn default_limit() -> EscapeLimit{
+    256
+}
+
+/// One image described in a job file.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct RenderJob{
+    pub resolution: Resolution,
+    #[serde(flatten)]
+    pub view:       JobView,
+    #[serde(default = "default_limit")]
+    pub limit:      EscapeLimit,
+    /// `grayscale`, one of `PALETTE_NAMES`, or the path of a `.ggr` gradient or of a CSV one with any
+    /// other extension. Grayscale if absent.
+    #[serde(default)]
+    pub palette:    Option<String>,
+    pub output:     PathBuf,
+}
+
+impl RenderJob{
+    pub fn rect(&self) -> Rect<f32>{
+        match self.view {
+            JobView::Rect{rect} => rect,
+            JobView::Center{center: (re, im), zoom} => Rect::from_center_zoom(Complex::<f32>{re, im}, zoom, &self.resolution),
+        }
+    }
+
+    /// The gradient the job is colored with, `None` for grayscale.
+    pub fn gradient(&self) -> Result<Option<Gradient>, LoadPaletteError>{
+        match self.palette.as_deref() {
+            None | Some("grayscale") => Ok(None),
+            Some(path) if path.ends_with(".ggr") => Gradient::from_ggr(path).map(Some),
+            Some(path) if Path::new(path).extension().is_some() => Gradient::from_csv(path).map(Some),
+            Some(name) => Ok(Some(Gradient::named(name)?)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JobFile{
+    #[serde(alias = "job")]
+    jobs: Vec<RenderJob>,
+}
+
+#[derive(Debug)]
+pub enum JobError{
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    /// The job's limit is 0, which would render the whole view as inside the set.
+    ZeroLimit{output: PathBuf},
+    /// The job's palette is neither a built-in name nor a readable gradient file.
+    Palette{output: PathBuf, source: LoadPaletteError},
+    Rect{output: PathBuf, source: RectError},
+}
+
+impl fmt::Display for JobError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            JobError::Io(err) => write!(f, "could not read job file: {err}"),
+            JobError::Toml(err) => write!(f, "invalid job file: {err}"),
+            JobError::Json(err) => write!(f, "invalid job file: {err}"),
+            JobError::ZeroLimit{output} => write!(f, "job for {} has a limit of 0", output.display()),
+            JobError::Palette{output, source} => write!(f, "job for {}: {source}", output.display()),
+            JobError::Rect{output, source} => write!(f, "job for {}: {source}", output.display()),
+        }
+    }
+}
+
+impl Error for JobError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            JobError::Io(err) => Some(err),
+            JobError::Toml(err) => Some(err),
+            JobError::Json(err) => Some(err),
+            JobError::ZeroLimit{..} => None,
+            JobError::Palette{source, ..} => Some(source),
+            JobError::Rect{source, ..} => Some(source),
+        }
+    }
+}
+
+impl From<io::Error> for JobError{
+    fn from(err: io::Error) -> Self{
+        JobError::Io(err)
+    }
+}
+
+/// Checks every job up front, so a bad one fails the whole file instead of halfway through a batch.
+fn validate_jobs(jobs: Vec<RenderJob>) -> Result<Vec<RenderJob>, JobError>{
+    for job in &jobs {
+        let output = || job.output.clone();
+        if job.limit == 0 {
+            return Err(JobError::ZeroLimit{output: output()});
+        }
+        job.gradient().map_err(|source| JobError::Palette{output: output(), source})?;
+        job.rect().validate().map_err(|source| JobError::Rect{output: output(), source})?;
+    }
+    Ok(jobs)
+}
+
+/// Parses the `[[job]]` tables of a TOML document.
+///
+/// ```
+/// use mandelbrot::geometry::{Range, Rect, Resolution};
+/// use mandelbrot::job::{parse_jobs_toml, JobView};
+///
+/// let text = r#"
+///     [[job]]
+///     resolution = {width = 640, height = 480}
+///     rect = {x = {min = -2.0, max = 1.0}, y = {min = -1.0, max = 1.0}}
+///     limit = 500
+///     palette = "viridis"
+///     output = "full.png"
+///
+///     [[job]]
+///     resolution = {width = 320, height = 240}
+///     center = [-0.75, 0.1]
+///     zoom = 8.0
+///     output = "zoomed.png"
+/// "#;
+/// let jobs = parse_jobs_toml(text).unwrap();
+/// assert_eq!(jobs.len(), 2);
+///
+/// let rect = Rect{x: Range{min: -2.0, max: 1.0}, y: Range{min: -1.0, max: 1.0}};
+/// assert_eq!(jobs[0].resolution, Resolution{width: 640, height: 480});
+/// assert_eq!(jobs[0].view, JobView::Rect{rect});
+/// assert_eq!((jobs[0].limit, jobs[0].palette.as_deref()), (500, Some("viridis")));
+/// assert_eq!(jobs[0].output.to_str(), Some("full.png"));
+///
+/// assert_eq!(jobs[1].resolution, Resolution{width: 320, height: 240});
+/// assert_eq!(jobs[1].view, JobView::Center{center: (-0.75, 0.1), zoom: 8.0});
+/// assert_eq!((jobs[1].limit, jobs[1].palette.as_deref()), (256, None));
+/// assert_eq!(jobs[1].output.to_str(), Some("zoomed.png"));
+/// let zoomed = jobs[1].rect();
+/// assert!(((zoomed.x.min + zoomed.x.max) / 2.0 + 0.75).abs() < 1e-6);
+/// assert!(((zoomed.y.min + zoomed.y.max) / 2.0 - 0.1).abs() < 1e-6);
+/// ```
+///
+/// ```
+/// use mandelbrot::job::{parse_jobs_toml, JobError};
+///
+/// let text = r#"
+///     [[job]]
+///     resolution = {width = 64, height = 48}
+///     center = [-0.5, 0.0]
+///     zoom = 1.0
+///     limit = 0
+///     output = "zero.png"
+/// "#;
+/// assert!(matches!(parse_jobs_toml(text), Err(JobError::ZeroLimit{..})));
+///
+/// let unknown_palette = text.replace("limit = 0", r#"palette = "fire""#);
+/// assert!(matches!(parse_jobs_toml(&unknown_palette), Err(JobError::Palette{..})));
+/// let empty_view = text.replace("limit = 0", "").replace("zoom = 1.0", "zoom = 0.0");
+/// assert!(matches!(parse_jobs_toml(&empty_view), Err(JobError::Rect{..})));
+/// ```
+pub fn parse_jobs_toml(text: &str) -> Result<Vec<RenderJob>, JobError>{
+    toml::from_str::<JobFile>(text).map_err(JobError::Toml).and_then(|file| validate_jobs(file.jobs))
+}
+
+/// Parses a JSON document of the form `{"jobs": [...]}`.
+///
+/// ```
+/// use mandelbrot::geometry::{Range, Rect, Resolution};
+/// use mandelbrot::job::{parse_jobs_json, JobError, JobView};
+///
+/// let text = r#"{"jobs": [
+///     {"resolution": {"width": 64, "height": 48}, "rect": {"x": {"min": -2.0, "max": 1.0}, "y": {"min": -1.0, "max": 1.0}},
+///      "palette": "twilight", "output": "full.png"},
+///     {"resolution": {"width": 32, "height": 32}, "center": [-0.75, 0.1], "zoom": 4.0, "limit": 1000, "output": "zoomed.png"}
+/// ]}"#;
+/// let jobs = parse_jobs_json(text).unwrap();
+/// let rect = Rect{x: Range{min: -2.0, max: 1.0}, y: Range{min: -1.0, max: 1.0}};
+/// assert_eq!(jobs[0].view, JobView::Rect{rect});
+/// assert_eq!((jobs[0].limit, jobs[0].palette.as_deref()), (256, Some("twilight")));
+/// assert_eq!(jobs[1].resolution, Resolution{width: 32, height: 32});
+/// assert_eq!(jobs[1].view, JobView::Center{center: (-0.75, 0.1), zoom: 4.0});
+/// assert_eq!((jobs[1].limit, jobs[1].palette.as_deref()), (1000, None));
+///
+/// assert!(matches!(parse_jobs_json(&text.replace("1000", "0")), Err(JobError::ZeroLimit{..})));
+/// assert!(matches!(parse_jobs_json(r#"{"jobs": [{"output": "x.png"}]}"#), Err(JobError::Json(_))));
+/// ```
+pub fn parse_jobs_json(text: &str) -> Result<Vec<RenderJob>, JobError>{
+    serde_json::from_str::<JobFile>(text).map_err(JobError::Json).and_then(|file| validate_jobs(file.jobs))
+}
+
+/// Reads a job file, as JSON if it ends in `.json` and as TOML otherwise.
+pub fn load_jobs(path: impl AsRef<Path>) -> Result<Vec<RenderJob>, JobError>{
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => parse_jobs_json(&text),
+        _ => parse_jobs_toml(&text),
+    }
+}
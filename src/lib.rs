@@ -0,0 +1,38 @@
+//! Escape-time rendering of the Mandelbrot set.
+//!
+//! Rendering a single tile into a grayscale buffer:
+//!
+//! ```
+//! use mandelbrot::color::write_data;
+//! use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+//! use mandelbrot::geometry::{Rect, Resolution};
+//! use mandelbrot::image::Image;
+//!
+//! let resolution = Resolution{width: 64, height: 48};
+//! let rect = Rect::from_bounds(-2.0, 1.0, -1.5, 1.5).unwrap();
+//!
+//! let mut image = Image::new(&resolution);
+//! let calculations = make_calculations(resolution, rect, 256, Precision::Single, EscapeParams::default()).unwrap();
+//! let written = write_data(&mut image, calculations, 256, 1.0).unwrap();
+//!
+//! let pixels: Vec<u8> = image.data.to_vec();
+//! assert_eq!(written, 64 * 48);
+//! assert_eq!(pixels.len(), 64 * 48);
+//! ```
+
+pub mod animation;
+pub mod atlas;
+pub mod color;
+pub mod compute;
+pub mod geometry;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod grid;
+pub mod image;
+#[cfg(feature = "interactive")]
+pub mod interactive;
+pub mod job;
+pub mod metadata;
+pub mod perturbation;
+pub mod render;
+pub mod simd;
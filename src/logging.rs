@@ -0,0 +1,34 @@
+use env_logger::{Env, Logger};
+use indicatif::ProgressBar;
+use log::{Log, Metadata, Record};
+
+/// Forwards to `env_logger`, hiding the progress bar while a line is written so the two don't garble
+/// each other.
+struct ProgressLogger{
+    inner:    Logger,
+    progress: ProgressBar,
+}
+
+impl Log for ProgressLogger{
+    fn enabled(&self, metadata: &Metadata) -> bool{
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record){
+        if self.inner.matches(record) {
+            self.progress.suspend(|| self.inner.log(record));
+        }
+    }
+
+    fn flush(&self){
+        self.inner.flush();
+    }
+}
+
+/// Installs the logger, filtered through `RUST_LOG` and defaulting to `info`.
+pub fn init(progress: ProgressBar){
+    let inner = env_logger::Builder::from_env(Env::default().default_filter_or("info")).build();
+    log::set_max_level(inner.filter());
+    log::set_boxed_logger(Box::new(ProgressLogger{inner, progress}))
+        .expect("the logger is only installed once");
+}
@@ -1,176 +1,383 @@
+mod cli;
+mod logging;
+
 use std::error::Error;
-use std::fs::File;
-use image::codecs::png::PngEncoder;
-use image::{ColorType, ExtendedColorType, ImageEncoder};
-use num::{Complex, pow};
-use num::integer::{div_ceil, div_floor};
-use rayon::prelude::*;
-
-type EscapeLimit = u16;
-
-fn calculate_escape_time(c: Complex<f32>, limit: EscapeLimit) -> Option<EscapeLimit>{
-    let mut z = Complex::<f32> {re: 0.0, im: 0.0};
-    for i in 0..limit{
-        let norm_sqr = z.norm_sqr();
-        if norm_sqr > 4.0{
-            return Some(i);
-        } else if (i > 0) && (norm_sqr <= 10e-6){
-            return None;
-        } else {
-            z = z * z + c;
-        }
-    }
-    None
+use std::fmt;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use mandelbrot::animation::{render_frames, zoom_sequence};
+use mandelbrot::atlas::{list_tiles, run_atlas, stitch_tiles, tile_rect, AtlasConfig, TileOutcome, CHECKPOINT_FILE_NAME, MANIFEST_FILE_NAME};
+use mandelbrot::color::{write_dithered_data, write_interior_data, write_l16_data, write_normalized_data, write_shaded_data, write_stripe_data, ColorScale, Dither, InteriorColoring};
+use mandelbrot::compute::{make_calculations, make_distance_calculations, make_escape_outcomes, make_smooth_calculations, make_stripe_calculations, AdaptiveLimit, EscapeLimit, EscapeParams, Precision, SMALL_LIMIT};
+use mandelbrot::geometry::Rect;
+#[cfg(feature = "gpu")]
+use mandelbrot::gpu::{make_calculations_gpu, GpuRenderer};
+use mandelbrot::grid::{save_exr, save_normalized, save_raw, EscapeGrid};
+use mandelbrot::image::{mip_level_path, mip_levels, prepare_output_dir, save_image, save_image_with_metadata, stream_png, Image, OutputFormat, PixelFormat};
+use mandelbrot::job::{load_jobs, RenderJob};
+use mandelbrot::metadata::RenderMetadata;
+use mandelbrot::render::{render, render_parallel, ProgressiveRender, RenderConfig};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use log::{debug, error, info, warn};
+
+use cli::{Args, ArgsError, RenderMode};
+
+/// An error together with the file or directory it happened on.
+#[derive(Debug)]
+struct PathError{
+    path:   String,
+    source: Box<dyn Error>,
 }
 
-
-fn make_lerp(input: (f32, f32), output: (f32, f32)) -> impl Fn(f32) -> f32 {
-    let a = (output.1 - output.0) / (input.1 - input.0);
-    let b =  output.0 - (input.0 * a);
-    move |x| x*a + b
+impl fmt::Display for PathError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "{}: {}", self.path, self.source)
+    }
 }
 
+impl Error for PathError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        Some(self.source.as_ref())
+    }
+}
 
-#[derive(Clone)]
-struct Resolution{
-    width: u32, height: u32
+/// Attaches `path` to an error, for `map_err`.
+fn at<E: Into<Box<dyn Error>>>(path: impl fmt::Display) -> impl FnOnce(E) -> PathError{
+    move |err| PathError{path: path.to_string(), source: err.into()}
 }
-struct Range<T>{
-    min: T,
-    max: T,
+
+/// An atlas run stopped by Ctrl-C before every tile was rendered.
+#[derive(Debug)]
+struct Cancelled{
+    tiles_left: u64,
 }
-struct Rect<T>{
-    x: Range<T>,
-    y: Range<T>,
+
+impl fmt::Display for Cancelled{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "stopped early, {} tiles are left for the next run", self.tiles_left)
+    }
 }
 
-fn make_calculations(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit)
-                     -> impl Iterator<Item = ((u32, u32), Option<EscapeLimit>)> {
+impl Error for Cancelled{}
+
+/// Shell convention for a process ended by SIGINT.
+const CANCELLED_EXIT_CODE: u8 = 130;
+
+/// Step of the first pass of `--progressive`.
+const PROGRESSIVE_STEP: u32 = 16;
+
+fn render_single(args: Args) -> Result<(), Box<dyn Error>>{
+    let output_format = args.output_format();
+    let Args{resolution, limit, rect, output, format, normalize, scale, dither, dither_seed, stripes, shade, interior, gamma, raw, exr, normalized, mmap, gpu, progressive, ..} = args;
+
+    let mut image = match &mmap {
+        Some(path) => Image::mapped(&resolution, format, path).map_err(at(path.display()))?,
+        None => Image::with_format(&resolution, format),
+    };
+    let params = EscapeParams::default();
+    let metadata = RenderMetadata{rect, resolution, limit, kind: params.kind, overlap: 0};
+    let precision = Precision::for_rect(&rect, &resolution);
+    debug!("Starting calculations for {output}");
+    if let Some(frequency) = stripes {
+        let stripes = make_stripe_calculations(resolution, rect, limit, precision, params, frequency).expect("validated by Args::parse");
+        write_stripe_data(&mut image, stripes, gamma).expect("calculations match the image resolution");
+    } else if let Some(light) = shade {
+        let distances = make_distance_calculations(resolution, rect, limit, precision, params).expect("validated by Args::parse");
+        let grid = EscapeGrid::collect(&resolution, distances).expect("calculations match the image resolution");
+        let pixel_size = rect.x.span() / resolution.width as f32;
+        write_shaded_data(&mut image, &grid, pixel_size, light).expect("grid matches the image resolution");
+    } else if interior != InteriorColoring::default() {
+        let outcomes = make_escape_outcomes(resolution, rect, limit, precision, params).expect("validated by Args::parse");
+        write_interior_data(&mut image, outcomes, limit, gamma, interior).expect("calculations match the image resolution");
+    } else if normalize || raw.is_some() || scale != ColorScale::Linear || dither != Dither::Off || progressive {
+        let color = |image: &mut Image, grid: &EscapeGrid| {
+            let written = match (normalize, format) {
+                (true, _) => write_normalized_data(image, grid),
+                (false, PixelFormat::L16) => write_l16_data(image, grid.iter(), limit, gamma),
+                (false, _) => write_dithered_data(image, grid.iter(), limit, scale, gamma, dither, dither_seed),
+            };
+            written.expect("grid matches the image resolution");
+        };
+        let grid = if progressive {
+            let mut progressive = ProgressiveRender::new(resolution, rect, limit, precision, params, PROGRESSIVE_STEP)?;
+            while let Some(step) = progressive.refine() {
+                if step > 1 {
+                    color(&mut image, progressive.grid());
+                    save_image(&image, &output, output_format).map_err(at(&output))?;
+                    info!("Wrote {output} at 1/{step} of the resolution");
+                }
+            }
+            progressive.into_grid()
+        } else {
+            EscapeGrid::collect(&resolution, make_calculations(resolution, rect, limit, precision, params).expect("validated by Args::parse"))
+                .expect("calculations match the image resolution")
+        };
+        if let Some(raw) = &raw {
+            save_raw(&grid, raw).map_err(at(raw))?;
+            info!("Wrote {raw}");
+        }
+        color(&mut image, &grid);
+    } else if gpu {
+        render_on_gpu(&mut image, rect, limit, gamma);
+    } else {
+        let stats = render_parallel(&mut image, rect, limit, precision, params, gamma).expect("validated by Args::parse");
+        debug!("Rendered {output}: {stats:?}");
+    }
+    if exr.is_some() || normalized.is_some() {
+        let smooth = make_smooth_calculations(resolution, rect, limit, precision, params).expect("validated by Args::parse");
+        let grid = EscapeGrid::collect(&resolution, smooth).expect("calculations match the image resolution");
+        if let Some(exr) = &exr {
+            save_exr(&grid, exr).map_err(at(exr))?;
+            info!("Wrote {exr}");
+        }
+        if let Some(normalized) = &normalized {
+            save_normalized(&grid, limit, normalized).map_err(at(normalized))?;
+            info!("Wrote {normalized}");
+        }
+    }
 
-    let Rect{x: Range{min: x_min, max: x_max} ,y: Range{min: y_min, max: y_max}} = rect;
+    let saved = match output_format {
+        OutputFormat::Png if mmap.is_some() => stream_png(&image, &output, Some(&metadata)),
+        OutputFormat::Png => save_image_with_metadata(&image, &output, &metadata),
+        _ => save_image(&image, &output, output_format),
+    };
+    saved.map_err(at(&output))?;
+    info!("Wrote {output}");
+    Ok(())
+}
 
-    let values =
-        (0..resolution.width.clone())
-        .map(
-            move |x|
-                (0..resolution.height.clone())
-                .map(move |y| (x, y))
-        )
-        .flatten()
-        .map({
-            let x_lerp = make_lerp((0_f32, resolution.width as f32), (x_min, x_max));
-            let y_lerp = make_lerp(( resolution.height as f32, 0_f32), (y_min, y_max));
+#[cfg(feature = "gpu")]
+fn render_on_gpu(image: &mut Image, rect: Rect<f32>, limit: EscapeLimit, gamma: f32){
+    let gpu = GpuRenderer::new().inspect_err(|err| warn!("{err}, rendering on the CPU instead")).ok();
+    let calculations = make_calculations_gpu(gpu.as_ref(), image.resolution, rect, limit).expect("validated by Args::parse");
+    let written = match image.format {
+        PixelFormat::L16 => write_l16_data(image, calculations, limit, gamma),
+        _ => mandelbrot::color::write_data(image, calculations, limit, gamma),
+    };
+    written.expect("calculations match the image resolution");
+}
 
-            move |(x, y)| {
-                let x_c = x_lerp(x as f32);
-                let y_c = y_lerp(y as f32);
-                let c = Complex::<f32>{re: x_c, im: y_c};
-                ((x, y), calculate_escape_time(c, limit))
-            }
-            .clone()
-        });
-    values
+#[cfg(not(feature = "gpu"))]
+fn render_on_gpu(_: &mut Image, _: Rect<f32>, _: EscapeLimit, _: f32){
+    unreachable!("`--gpu` is only accepted by builds with the gpu feature")
 }
 
+#[cfg(feature = "interactive")]
+fn explore(args: Args) -> Result<(), Box<dyn Error>>{
+    let view = mandelbrot::interactive::explore(args.resolution, args.rect, args.limit)?;
+    println!("--x-min {} --x-max {} --y-min {} --y-max {}", view.x.min, view.x.max, view.y.min, view.y.max);
+    Ok(())
+}
 
-struct Image{
-    resolution: Resolution,
-    data:       Box<[u8]>,
+/// Renders one tile of the atlas grid on its own, at the resolution and with the options of a single
+/// image.
+fn render_atlas_tile(args: Args) -> Result<(), Box<dyn Error>>{
+    let (x, y) = (args.x_index.expect("validated by Args::parse"), args.y_index.expect("validated by Args::parse"));
+    let rect = tile_rect(args.rect, args.cols, args.rows, x, y);
+    rect.validate()?;
+    debug!("Tile ({x}, {y}) covers {rect:?}");
+    render_single(Args{rect, ..args})
 }
 
-impl Image{
-    fn new(res: &Resolution) -> Self{
-        let data = vec![0; (res.width as usize) * (res.height as usize)];
+fn render_job(job: &RenderJob) -> Result<(), PathError>{
+    let output = job.output.display();
+    let rect = job.rect();
+    let metadata = RenderMetadata{rect, resolution: job.resolution, limit: job.limit, kind: EscapeParams::default().kind, overlap: 0};
+    let gradient = job.gradient().map_err(at(&output))?;
 
-        Image{
-            resolution: res.clone(),
-            data: data.into_boxed_slice()
-        }
+    if job.limit < SMALL_LIMIT {
+        warn!("{output}: a limit of {} leaves most of the boundary out", job.limit);
+    }
+    debug!("Starting calculations for {output}");
+    let mut config = RenderConfig::builder().resolution(job.resolution).rect(rect).limit(job.limit);
+    if let Some(gradient) = gradient {
+        config = config.palette(gradient);
     }
+    let config = config.build().map_err(at(&output))?;
+    let image = render(&config);
+
+    let saved = match OutputFormat::from_path(&job.output).unwrap_or(OutputFormat::Png) {
+        OutputFormat::Png => save_image_with_metadata(&image, &job.output, &metadata),
+        format => save_image(&image, &job.output, format),
+    };
+    saved.map_err(at(&output))
 }
 
-
-fn write_data(
-        img: &mut Image,
-        data: impl Iterator<Item=((u32, u32), Option<EscapeLimit>)>,
-        escape_limit: EscapeLimit,
-    ) {
-
-    let const_mul =  255_f32 / escape_limit as f32;
-    for (position, value) in data {
-        let index = (position.0 + position.1 * img.resolution.width) as usize;
-
-        if let Some(pixel) = img.data.get_mut(index) {
-            *pixel = match value {
-                None => { 0 }
-                Some(val) => { 255 - (val as f32 * const_mul) as u8 }
+fn render_jobs(args: Args) -> Result<(), Box<dyn Error>>{
+    let config = args.config.expect("validated by Args::parse");
+    let jobs = load_jobs(&config).map_err(at(&config))?;
+
+    let mut failed = 0;
+    for job in &jobs {
+        match render_job(job) {
+            Ok(()) => info!("Wrote {}", job.output.display()),
+            Err(err) => {
+                error!("{err}");
+                failed += 1;
             }
         }
     }
-
+    if failed > 0 {
+        return Err(format!("{failed} of {} jobs failed", jobs.len()).into());
+    }
+    Ok(())
 }
 
-fn save_image(img: &Image, file_name: &str) -> Result<(), Box<dyn Error>>{
-    let output = File::create(format!("{file_name}.png"))?;
-    let encoder = PngEncoder::new(output);
+fn render_zoom(args: Args, progress: ProgressBar) -> Result<(), Box<dyn Error>>{
+    let output_dir = args.output_dir_or("frames/");
+    let Args{resolution, limit, rect: start, quiet, frames, target, zoom, gamma, ..} = args;
+    prepare_output_dir(&output_dir)?;
+
+    progress.set_length(frames as u64);
+    if !quiet {
+        progress.set_style(ProgressStyle::with_template("{wide_bar} {pos}/{len} frames ({percent}%, ETA {eta})")?);
+        progress.set_draw_target(ProgressDrawTarget::stderr());
+    }
 
-    encoder
-    .write_image(
-        &img.data,
-        img.resolution.width,
-        img.resolution.height,
-        ColorType::L8.into(),
-    )
-    .expect("Error while trying to save this shit");
+    let views = zoom_sequence(start, target, zoom, frames, &resolution);
+    let rendered = render_frames(&views, resolution, limit, EscapeParams::default(), gamma, &output_dir, |_| progress.inc(1));
+    progress.finish_and_clear();
 
+    rendered.map_err(at(&output_dir))?;
+    info!("Wrote {frames} frames to {output_dir}");
     Ok(())
 }
 
-fn main(){
-    const RESOLUTION: Resolution = Resolution{width: 1024*2*2*2, height: 1024*2*2*2};
-    const LIMIT: EscapeLimit = 256;
-    let dest = "atlas/";
-    std::fs::create_dir_all(dest).unwrap();
+fn stitch_atlas(args: Args) -> Result<(), Box<dyn Error>>{
+    let output_dir = args.output_dir_or("atlas/");
+    let Args{resolution, rect: region, output, transparent, mipmaps, ..} = args;
 
-    let rect_lin_num = 128;
+    let tiles = list_tiles(&output_dir).map_err(at(&output_dir))?;
+    debug!("Stitching {} tiles from {output_dir}", tiles.len());
 
-    let x_rect_lerp = make_lerp((0.0, rect_lin_num as f32), (-2.0, 1.0) );
-    let y_rect_lerp = make_lerp((0.0, rect_lin_num as f32), (-1.5, 1.5) );
+    let format = if transparent { PixelFormat::La8 } else { PixelFormat::L8 };
+    let image = stitch_tiles(&tiles, region, resolution, format)?;
+    save_image(&image, &output, OutputFormat::Png).map_err(at(&output))?;
+    info!("Wrote {output} from {} tiles", tiles.len());
 
-    let atlas_squares =
-        (0..rect_lin_num)
-        .into_par_iter()
-        .map(move |x| (0..rect_lin_num).into_par_iter().map(move|y|(x, y)))
-        .flatten()
-        .map(|(x_i, y_i)|{
-            let rect = {
-                    let x_range = Range::<f32>{min: x_rect_lerp(x_i as f32) , max: x_rect_lerp((x_i + 1) as
-                    f32)};
-                    let y_range = Range::<f32>{min: y_rect_lerp(y_i as f32) , max: y_rect_lerp((y_i + 1) as
-                    f32)};
+    if mipmaps {
+        for (index, level) in mip_levels(&image, 2).iter().enumerate() {
+            let path = mip_level_path(&output, index + 1);
+            save_image(level, &path, OutputFormat::Png).map_err(at(path.display()))?;
+        }
+        info!("Wrote the mip levels of {output}");
+    }
+    Ok(())
+}
 
-                    Rect::<f32>{x: x_range,y: y_range}
-            };
+fn render_atlas(args: Args, progress: ProgressBar) -> Result<(), Box<dyn Error>>{
+    let output_dir = args.output_dir_or("atlas/");
+    let Args{resolution, limit, adaptive_k, max_limit, rect: region, cols, rows, quiet, min_contrast, min_mixed, force, prioritize, fill_uniform, tile_timeout, normalize, gamma, threads, overlap, ..} = args;
+    let dir = PathBuf::from(output_dir.trim_end_matches('/'));
+    prepare_output_dir(&dir)?;
 
-            let string_end = format!("[{:02.3},{:02.3}]_[{:02.3},{:02.3}]", &rect.x.min, &rect.x
-            .max, &rect.y.min, &rect.y.max);
+    progress.set_length(cols as u64 * rows as u64);
+    if !quiet {
+        progress.set_style(ProgressStyle::with_template("{wide_bar} {pos}/{len} tiles ({percent}%, ETA {eta})")?);
+        progress.set_draw_target(ProgressDrawTarget::stderr());
+    }
 
-            let file_name = format!("atlas/mandelbrot_{}", &string_end);
-            let mut image = Image::new(&RESOLUTION);
-            let calculations = make_calculations(RESOLUTION, rect, LIMIT);
-            println!("Starting calculations for {}", &string_end);
-            write_data(&mut image, calculations, LIMIT);
+    let cancel = Arc::new(AtomicBool::new(false));
+    let handler_cancel = Arc::clone(&cancel);
+    let installed = ctrlc::set_handler(move || {
+        if handler_cancel.swap(true, Ordering::Relaxed) {
+            std::process::exit(CANCELLED_EXIT_CODE.into());
+        }
+        warn!("Interrupted, finishing the tiles in progress (press Ctrl-C again to quit now)");
+    });
+    if let Err(err) = installed {
+        warn!("Ctrl-C will not stop the atlas cleanly: {err}");
+    }
 
-            if (image.data.iter().max().unwrap() - image.data.iter().min().unwrap()) > 20 {
-                println!("Writing file for {}", &string_end );
-                save_image(&image, &file_name).unwrap();
-            } else {
-                println!("Skipping {}", &string_end);
+    let config = AtlasConfig{
+        region, cols, rows, resolution, limit, dir: dir.clone(), min_contrast, min_mixed, force, prioritize, fill_uniform, tile_timeout, normalize, gamma, threads, overlap,
+        adaptive_limit: adaptive_k.map(|k| AdaptiveLimit{base: limit, k, max: max_limit}),
+        checkpoint: Some(dir.join(CHECKPOINT_FILE_NAME)),
+        manifest: Some(dir.join(MANIFEST_FILE_NAME)),
+        cancel: Some(cancel),
+    };
+    let stats = run_atlas(&config, |path, outcome| {
+        let tile_name = path.file_name().unwrap_or_default().to_string_lossy();
+        match outcome {
+            TileOutcome::Written => info!("Wrote {tile_name}"),
+            TileOutcome::Skipped => debug!("Skipping {tile_name}"),
+            TileOutcome::AlreadyDone => debug!("Already rendered {tile_name}"),
+            TileOutcome::TimedOut => warn!("Abandoned {tile_name} after exceeding the tile timeout"),
+            TileOutcome::Cancelled | TileOutcome::Failed(_) => {}
+        }
+        progress.inc(1);
+    });
+    progress.finish_and_clear();
+
+    info!(
+        "{} tiles: {} written, {} skipped, {} already rendered, {} timed out, {} failed",
+        stats.total_tiles, stats.written, stats.skipped, stats.already_done, stats.timed_out, stats.failures.len(),
+    );
+    info!(
+        "{} pixels computed, {:.1} iterations per pixel on average, {} inside the set, in {:.1?}",
+        stats.pixels_computed, stats.average_iterations(), stats.pixels_inside, stats.elapsed,
+    );
+    if let Some(err) = &stats.checkpoint_error {
+        warn!("{err}, the next run may render some tiles again");
+    }
+    if let Some(err) = &stats.manifest_error {
+        warn!("{err}, {MANIFEST_FILE_NAME} is out of date");
+    }
+    if !stats.failures.is_empty() {
+        for (path, err) in &stats.failures {
+            error!("{}: {err}", path.display());
+        }
+        return Err(format!("{} tiles could not be saved", stats.failures.len()).into());
+    }
+    if stats.cancelled > 0 {
+        return Err(Cancelled{tiles_left: stats.cancelled}.into());
+    }
+    info!("all finished");
+    Ok(())
+}
 
-            }
-        });
+// Failures are logged here rather than returned from `main`, which would print their `Debug` form.
+fn main() -> ExitCode{
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(ArgsError::Help) => {
+            print!("{}", cli::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{}", cli::USAGE);
+            return ExitCode::from(2);
+        }
+    };
 
+    let progress = ProgressBar::hidden();
+    logging::init(progress.clone());
+    if args.mode != RenderMode::Jobs && args.limit < SMALL_LIMIT {
+        warn!("A limit of {} leaves most of the boundary out", args.limit);
+    }
 
-    let _: Vec<()> = atlas_squares.collect();
-    println!("all finished")
-}
\ No newline at end of file
+    let rendered = match args.mode {
+        RenderMode::Atlas  => render_atlas(args, progress),
+        RenderMode::Single => render_single(args),
+        RenderMode::Zoom   => render_zoom(args, progress),
+        RenderMode::Stitch => stitch_atlas(args),
+        RenderMode::Jobs   => render_jobs(args),
+        RenderMode::Tile   => render_atlas_tile(args),
+        #[cfg(feature = "interactive")]
+        RenderMode::Explore => explore(args),
+    };
+    match rendered {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) if err.is::<Cancelled>() => {
+            warn!("{err}");
+            ExitCode::from(CANCELLED_EXIT_CODE)
+        }
+        Err(err) => {
+            error!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use crate::compute::{EscapeLimit, FractalKind};
+use crate::geometry::{Range, Rect, Resolution};
+
+/// Render parameters stored in the text chunks of a PNG, so a tile can be re-rendered from the file alone.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RenderMetadata{
+    pub rect:       Rect<f32>,
+    pub resolution: Resolution,
+    pub limit:      EscapeLimit,
+    pub kind:       FractalKind,
+    /// Pixels the image extends past `rect` on every side, beyond the `resolution` covering it.
+    pub overlap:    u32,
+}
+
+const KEYS: [&str; 9] = ["x-min", "x-max", "y-min", "y-max", "width", "height", "limit", "fractal", "overlap"];
+
+#[derive(Debug)]
+pub enum MetadataError{
+    Io(io::Error),
+    Decoding(png::DecodingError),
+    Missing(&'static str),
+    Invalid{key: &'static str, value: String},
+}
+
+impl fmt::Display for MetadataError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            MetadataError::Io(err) => write!(f, "could not read image: {err}"),
+            MetadataError::Decoding(err) => write!(f, "could not decode image: {err}"),
+            MetadataError::Missing(key) => write!(f, "image has no `{key}` metadata"),
+            MetadataError::Invalid{key, value} => write!(f, "invalid `{key}` metadata `{value}`"),
+        }
+    }
+}
+
+impl Error for MetadataError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            MetadataError::Io(err) => Some(err),
+            MetadataError::Decoding(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl RenderMetadata{
+    /// Keyword and text pairs. Floats are written in their shortest round-tripping form, so
+    /// parsing them back yields the exact same bounds.
+    pub fn to_text(&self) -> Vec<(String, String)>{
+        let values = [
+            self.rect.x.min.to_string(),
+            self.rect.x.max.to_string(),
+            self.rect.y.min.to_string(),
+            self.rect.y.max.to_string(),
+            self.resolution.width.to_string(),
+            self.resolution.height.to_string(),
+            self.limit.to_string(),
+            self.kind.to_string(),
+            self.overlap.to_string(),
+        ];
+        KEYS.iter().map(|key| key.to_string()).zip(values).collect()
+    }
+
+    pub fn from_text<'a>(entries: impl IntoIterator<Item = (&'a str, &'a str)> + Clone) -> Result<RenderMetadata, MetadataError>{
+        let get = |key: &'static str| {
+            entries.clone().into_iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, value)| value)
+                .ok_or(MetadataError::Missing(key))
+        };
+        fn parse<T: std::str::FromStr>(key: &'static str, value: &str) -> Result<T, MetadataError>{
+            value.parse().map_err(|_| MetadataError::Invalid{key, value: value.to_string()})
+        }
+
+        let [x_min, x_max, y_min, y_max, width, height, limit, kind, overlap] = KEYS;
+        Ok(RenderMetadata{
+            rect: Rect{
+                x: Range{min: parse(x_min, get(x_min)?)?, max: parse(x_max, get(x_max)?)?},
+                y: Range{min: parse(y_min, get(y_min)?)?, max: parse(y_max, get(y_max)?)?},
+            },
+            resolution: Resolution{width: parse(width, get(width)?)?, height: parse(height, get(height)?)?},
+            limit: parse(limit, get(limit)?)?,
+            kind: parse(kind, get(kind)?)?,
+            // Files from before overlapping tiles have none.
+            overlap: get(overlap).map_or(Ok(0), |value| parse(overlap, value))?,
+        })
+    }
+}
+
+pub fn read_metadata(path: impl AsRef<Path>) -> Result<RenderMetadata, MetadataError>{
+    let file = File::open(path).map_err(MetadataError::Io)?;
+    let reader = png::Decoder::new(BufReader::new(file)).read_info().map_err(MetadataError::Decoding)?;
+    let text = &reader.info().uncompressed_latin1_text;
+
+    RenderMetadata::from_text(text.iter().map(|chunk| (chunk.keyword.as_str(), chunk.text.as_str())))
+}
@@ -0,0 +1,275 @@
+//! Perturbation rendering for zooms past what f64 coordinates resolve, around a double-double reference
+//! orbit. Library only: the command line renders through `Rect<f32>` and does not reach this module.
+
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+use num::Complex;
+
+use crate::compute::EscapeLimit;
+use crate::geometry::Resolution;
+
+/// Unevaluated sum `hi + lo` of two f64s, roughly 32 significant digits. Only used for the reference
+/// orbit, so it only implements what that needs.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct DoubleDouble{
+    pub hi: f64,
+    pub lo: f64,
+}
+
+fn two_sum(a: f64, b: f64) -> DoubleDouble{
+    let s = a + b;
+    let bb = s - a;
+    DoubleDouble{hi: s, lo: (a - (s - bb)) + (b - bb)}
+}
+
+fn quick_two_sum(a: f64, b: f64) -> DoubleDouble{
+    let s = a + b;
+    DoubleDouble{hi: s, lo: b - (s - a)}
+}
+
+fn two_prod(a: f64, b: f64) -> DoubleDouble{
+    let p = a * b;
+    DoubleDouble{hi: p, lo: a.mul_add(b, -p)}
+}
+
+impl DoubleDouble{
+    pub fn to_f64(self) -> f64{
+        self.hi + self.lo
+    }
+
+    fn div_f64(self, d: f64) -> DoubleDouble{
+        let q1 = self.hi / d;
+        let p = two_prod(q1, d);
+        let s = two_sum(self.hi, -p.hi);
+        let q2 = (s.hi + (s.lo - p.lo + self.lo)) / d;
+        quick_two_sum(q1, q2)
+    }
+}
+
+impl From<f64> for DoubleDouble{
+    fn from(value: f64) -> Self{
+        DoubleDouble{hi: value, lo: 0.0}
+    }
+}
+
+impl Add for DoubleDouble{
+    type Output = DoubleDouble;
+    fn add(self, rhs: DoubleDouble) -> DoubleDouble{
+        let s = two_sum(self.hi, rhs.hi);
+        quick_two_sum(s.hi, s.lo + self.lo + rhs.lo)
+    }
+}
+
+impl Neg for DoubleDouble{
+    type Output = DoubleDouble;
+    fn neg(self) -> DoubleDouble{
+        DoubleDouble{hi: -self.hi, lo: -self.lo}
+    }
+}
+
+impl Sub for DoubleDouble{
+    type Output = DoubleDouble;
+    fn sub(self, rhs: DoubleDouble) -> DoubleDouble{
+        self + (-rhs)
+    }
+}
+
+impl Mul for DoubleDouble{
+    type Output = DoubleDouble;
+    fn mul(self, rhs: DoubleDouble) -> DoubleDouble{
+        let p = two_prod(self.hi, rhs.hi);
+        quick_two_sum(p.hi, p.lo + self.hi * rhs.lo + self.lo * rhs.hi)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDoubleDoubleError(pub String);
+
+impl std::fmt::Display for ParseDoubleDoubleError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "invalid decimal number `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseDoubleDoubleError{}
+
+/// Parses plain decimals such as `-1.7499999999999999999123`, keeping the digits an f64 would drop.
+///
+/// ```
+/// use mandelbrot::perturbation::DoubleDouble;
+///
+/// // Every f64 printed in full reads back as itself.
+/// for value in [0.1, -1.7499999999999998, 123.456, 0.000012345, 1.0 / 3.0, 0.0] {
+///     let parsed: DoubleDouble = format!("{value}").parse().unwrap();
+///     assert_eq!(parsed.to_f64(), value);
+/// }
+/// // The digits past an f64 end up in `lo`.
+/// let deep: DoubleDouble = "-1.00000000000000000001".parse().unwrap();
+/// assert_eq!(deep.hi, -1.0);
+/// assert!((deep.lo + 1e-20).abs() < 1e-30);
+///
+/// for invalid in ["", "-", ".", "1.2.3", "1e5", "0x10"] {
+///     assert!(invalid.parse::<DoubleDouble>().is_err(), "{invalid}");
+/// }
+/// ```
+impl FromStr for DoubleDouble{
+    type Err = ParseDoubleDoubleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>{
+        let err = || ParseDoubleDoubleError(s.to_string());
+        let (negative, digits) = match s.trim().strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.trim().strip_prefix('+').unwrap_or(s.trim())),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(err());
+        }
+
+        let mut value = DoubleDouble::default();
+        for digit in integer.chars().chain(fraction.chars()) {
+            let digit = digit.to_digit(10).ok_or_else(err)?;
+            value = value * DoubleDouble::from(10.0) + DoubleDouble::from(digit as f64);
+        }
+        for _ in 0..fraction.len() {
+            value = value.div_f64(10.0);
+        }
+
+        Ok(if negative { -value } else { value })
+    }
+}
+
+/// Orbit `Z_n` of a single reference point, computed in double-double and rounded to f64. Pixels then
+/// only iterate their small offset from it, which f64 represents well at any zoom.
+pub struct ReferenceOrbit{
+    pub center: (DoubleDouble, DoubleDouble),
+    pub orbit:  Vec<Complex<f64>>,
+}
+
+impl ReferenceOrbit{
+    /// Iterates until the reference escapes or reaches `limit`. The orbit always holds at least `Z_0 = 0`.
+    ///
+    /// ```
+    /// use mandelbrot::perturbation::{DoubleDouble, ReferenceOrbit};
+    /// use num::Complex;
+    ///
+    /// let point = |re: f64, im: f64| (DoubleDouble::from(re), DoubleDouble::from(im));
+    /// let z = |re, im| Complex{re, im};
+    /// // The orbit of 1 ends with the first iterate past the bailout.
+    /// assert_eq!(ReferenceOrbit::compute(point(1.0, 0.0), 100).orbit, [z(0.0, 0.0), z(1.0, 0.0), z(2.0, 0.0), z(5.0, 0.0)]);
+    /// // -1 cycles between -1 and 0 until the limit.
+    /// let cycle = ReferenceOrbit::compute(point(-1.0, 0.0), 6).orbit;
+    /// assert_eq!(cycle.len(), 7);
+    /// assert_eq!(cycle[5..], [z(-1.0, 0.0), z(0.0, 0.0)]);
+    /// ```
+    pub fn compute(center: (DoubleDouble, DoubleDouble), limit: EscapeLimit) -> ReferenceOrbit{
+        let (c_re, c_im) = center;
+        let (mut re, mut im) = (DoubleDouble::default(), DoubleDouble::default());
+        let mut orbit = Vec::with_capacity(limit as usize + 1);
+
+        for _ in 0..=limit {
+            let z = Complex::<f64>{re: re.to_f64(), im: im.to_f64()};
+            orbit.push(z);
+            if z.norm_sqr() > 4.0 {
+                break;
+            }
+            let next_re = re * re - im * im + c_re;
+            let next_im = DoubleDouble::from(2.0) * re * im + c_im;
+            re = next_re;
+            im = next_im;
+        }
+
+        ReferenceOrbit{center, orbit}
+    }
+}
+
+/// Escape time of the point `C + delta_c`, where `C` is the center of `reference`, iterating
+/// `δ = 2*Z_n*δ + δ^2 + δc`. When the pixel gets closer to zero than to the reference, or the
+/// reference runs out, the offset is rebased onto the start of the reference orbit, which avoids
+/// the glitches of plain perturbation. `reference` needs at least `Z_0` and `Z_1`, which
+/// `ReferenceOrbit::compute` always provides for a nonzero limit.
+///
+/// ```
+/// use mandelbrot::compute::{calculate_escape_time, Convergence, EscapeParams};
+/// use mandelbrot::perturbation::{calculate_escape_time_perturbation, DoubleDouble, ReferenceOrbit};
+/// use num::Complex;
+///
+/// // The orbit of 0 stays at 0, so the offset iterates as the plain `z^2 + c` of each point.
+/// let origin = ReferenceOrbit::compute((DoubleDouble::from(0.0), DoubleDouble::from(0.0)), 500);
+/// let params = EscapeParams{cardioid_check: false, convergence: Convergence::Off, ..EscapeParams::default()};
+/// for (re, im) in [(-0.75, 0.1), (0.3, 0.5), (-1.9, 0.0), (0.25, 0.0), (1.0, 1.0)] {
+///     let plain = calculate_escape_time(Complex{re: re as f32, im: im as f32}, 500, params);
+///     assert_eq!(calculate_escape_time_perturbation(&origin.orbit, Complex{re, im}, 500), plain, "{re} + {im}i");
+/// }
+/// ```
+pub fn calculate_escape_time_perturbation(reference: &[Complex<f64>], delta_c: Complex<f64>, limit: EscapeLimit) -> Option<EscapeLimit>{
+    if reference.len() < 2 {
+        return None;
+    }
+
+    let mut delta = Complex::<f64>{re: 0.0, im: 0.0};
+    let mut n = 0;
+    for i in 0..limit {
+        let z = reference[n] + delta;
+        if z.norm_sqr() > 4.0 {
+            return Some(i);
+        }
+
+        if z.norm_sqr() < delta.norm_sqr() || n + 1 >= reference.len() {
+            delta = z;
+            n = 0;
+        }
+
+        delta = reference[n] * delta * 2.0 + delta * delta + delta_c;
+        n += 1;
+    }
+    None
+}
+
+/// A view too deep for `Rect<f32>`: a high precision center and the width of the view.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DeepView{
+    pub center: (DoubleDouble, DoubleDouble),
+    pub span:   f64,
+}
+
+/// `make_calculations` for a `DeepView`, through one reference orbit at the view's center. Pixels are
+/// square, `span / resolution.width` wide.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, Convergence, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::perturbation::{make_calculations_perturbation, DeepView, DoubleDouble};
+///
+/// // A boundary region 2^-15 across, on bounds f32 holds exactly so both renders see the same points.
+/// let resolution = Resolution{width: 64, height: 64};
+/// let rect = Rect::from_bounds(-24368.0 / 32768.0, -24367.0 / 32768.0, 4319.0 / 32768.0, 4320.0 / 32768.0).unwrap();
+/// let center = |min: f32, max: f32| DoubleDouble::from((min as f64 + max as f64) / 2.0);
+/// let view = DeepView{center: (center(rect.x.min, rect.x.max), center(rect.y.min, rect.y.max)), span: rect.x.span() as f64};
+///
+/// // Perturbation has no convergence shortcut to end interior orbits early.
+/// let params = EscapeParams{convergence: Convergence::Off, ..EscapeParams::default()};
+/// let direct: Vec<_> = make_calculations(resolution, rect, 500, Precision::Double, params).unwrap().collect();
+/// let perturbed: Vec<_> = make_calculations_perturbation(resolution, view, 500).collect();
+/// assert_eq!(perturbed.len(), direct.len());
+/// // Only the odd pixel deep in the chaotic boundary comes out a few iterations apart.
+/// let differing = direct.iter().zip(&perturbed).filter(|(a, b)| a != b).count();
+/// assert!(differing * 100 < direct.len(), "{differing} pixels differ");
+/// ```
+pub fn make_calculations_perturbation(resolution: Resolution, view: DeepView, limit: EscapeLimit)
+                     -> impl Iterator<Item = ((u32, u32), Option<EscapeLimit>)> {
+    let reference = ReferenceOrbit::compute(view.center, limit);
+    let pixel_size = view.span / resolution.width as f64;
+    let (half_width, half_height) = (resolution.width as f64 / 2.0, resolution.height as f64 / 2.0);
+
+    (0..resolution.height)
+        .flat_map(move |y| (0..resolution.width).map(move |x| (x, y)))
+        .map(move |(x, y)| {
+            let delta_c = Complex::<f64>{
+                re: (x as f64 - half_width) * pixel_size,
+                im: (half_height - y as f64) * pixel_size,
+            };
+            ((x, y), calculate_escape_time_perturbation(&reference.orbit, delta_c, limit))
+        })
+}
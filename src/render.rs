@@ -0,0 +1,578 @@
+use std::error::Error;
+use std::fmt;
+
+use rayon::prelude::*;
+
+use crate::color::{gray_level_16, write_colored_lut_data, write_data, Gradient, GrayLut, PaletteLut};
+use crate::compute::{coordinate_mapper, escape_time_at, make_calculations, EscapeLimit, EscapeParams, Precision};
+use crate::geometry::{Rect, RectError, Resolution};
+use crate::grid::EscapeGrid;
+use crate::image::{encode_image, Image, OutputFormat, PixelFormat, SaveError};
+
+/// Row from which on the image repeats the rows above it upside down, if `rect` is centered on the
+/// real axis of a fractal that is symmetric about it. Row `y` then shows the conjugates of row
+/// `height - y`.
+fn mirror_start(resolution: Resolution, rect: Rect<f32>, params: EscapeParams) -> Option<usize>{
+    let symmetric = params.kind.is_symmetric_about_real_axis() && params.z0.im == 0.0 && rect.y.min == -rect.y.max;
+    (symmetric && resolution.height > 2).then_some(resolution.height as usize / 2 + 1)
+}
+
+/// Escape times a render came across, gathered while it computes them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RenderStats{
+    /// Fastest and slowest escapes, `None` when every pixel is inside the set.
+    pub min_escape:   Option<EscapeLimit>,
+    pub max_escape:   Option<EscapeLimit>,
+    pub inside_count: u64,
+    pub total:        u64,
+}
+
+impl RenderStats{
+    pub fn record(&mut self, escape_time: Option<EscapeLimit>){
+        self.total += 1;
+        match escape_time {
+            None => self.inside_count += 1,
+            Some(count) => {
+                self.min_escape = Some(self.min_escape.map_or(count, |min| min.min(count)));
+                self.max_escape = Some(self.max_escape.map_or(count, |max| max.max(count)));
+            }
+        }
+    }
+
+    /// Stats of both renders together.
+    pub fn merge(self, other: RenderStats) -> RenderStats{
+        let pick = |a: Option<EscapeLimit>, b: Option<EscapeLimit>, f: fn(EscapeLimit, EscapeLimit) -> EscapeLimit| match (a, b) {
+            (Some(a), Some(b)) => Some(f(a, b)),
+            (a, b) => a.or(b),
+        };
+        RenderStats{
+            min_escape:   pick(self.min_escape, other.min_escape, Ord::min),
+            max_escape:   pick(self.max_escape, other.max_escape, Ord::max),
+            inside_count: self.inside_count + other.inside_count,
+            total:        self.total + other.total,
+        }
+    }
+}
+
+/// Renders `rect` into a grayscale (`L8` or `L16`) `img`, computing its rows in parallel. Produces the
+/// same pixels as `write_data` or `write_l16_data` over `make_calculations`. When the rect is centered
+/// on the real axis of a symmetric fractal, only the upper half is computed and mirrored below, its
+/// stats counting the mirrored rows as well. Fails if `rect` does not pass `Rect::validate`.
+///
+/// ```
+/// use mandelbrot::color::write_data;
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Range, Rect, RectError, Resolution};
+/// use mandelbrot::image::Image;
+/// use mandelbrot::render::render_parallel;
+///
+/// let resolution = Resolution{width: 96, height: 64};
+/// let sequential = |rect| {
+///     let mut image = Image::new(&resolution);
+///     let calculations = make_calculations(resolution, rect, 128, Precision::Single, EscapeParams::default()).unwrap();
+///     write_data(&mut image, calculations, 128, 1.0).unwrap();
+///     image
+/// };
+///
+/// // Centered on the real axis, so the lower half is mirrored, and off it, so every row is computed.
+/// let symmetric = Rect{x: Range{min: -2.0, max: 1.0}, y: Range{min: -1.0, max: 1.0}};
+/// let asymmetric = Rect{x: Range{min: -2.0, max: 1.0}, y: Range{min: -0.7, max: 1.3}};
+/// for rect in [symmetric, asymmetric] {
+///     let mut parallel = Image::new(&resolution);
+///     render_parallel(&mut parallel, rect, 128, Precision::Single, EscapeParams::default(), 1.0).unwrap();
+///     assert_eq!(parallel.data, sequential(rect).data);
+/// }
+///
+/// // An empty rect would otherwise come out as a solid image.
+/// let empty = Rect{x: Range{min: 0.5, max: 0.5}, y: Range{min: -1.0, max: 1.0}};
+/// let mut image = Image::new(&resolution);
+/// assert!(matches!(render_parallel(&mut image, empty, 128, Precision::Single, EscapeParams::default(), 1.0), Err(RectError::Empty{axis: "x", ..})));
+/// ```
+pub fn render_parallel(img: &mut Image, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams, gamma: f32)
+                     -> Result<RenderStats, RectError>{
+    debug_assert!(matches!(img.format, PixelFormat::L8 | PixelFormat::L16));
+    rect.validate()?;
+
+    let resolution = img.resolution;
+    let format = img.format;
+    let bytes_per_pixel = format.bytes_per_pixel();
+    let row_bytes = resolution.width.max(1) as usize * bytes_per_pixel;
+    let mapper = coordinate_mapper(resolution, rect, precision, params.orientation);
+    let lut = GrayLut::new(limit, gamma);
+
+    let mirror_start = mirror_start(resolution, rect, params);
+    let computed_rows = mirror_start.unwrap_or(resolution.height as usize);
+    let split = (computed_rows * row_bytes).min(img.data.len());
+    let (computed, mirrored) = img.data.split_at_mut(split);
+    let row_stats: Vec<RenderStats> = computed
+        .par_chunks_mut(row_bytes)
+        .enumerate()
+        .map(|(y, row)| {
+            let mut stats = RenderStats::default();
+            for (x, pixel) in row.chunks_exact_mut(bytes_per_pixel).enumerate() {
+                let c = mapper(x as f64, y as f64);
+                let escape_time = escape_time_at(c, limit, precision, params);
+                stats.record(escape_time);
+                match format {
+                    PixelFormat::L16 => pixel.copy_from_slice(&gray_level_16(escape_time, 65535_f32 / limit as f32, gamma).to_be_bytes()),
+                    _ => pixel[0] = lut.level(escape_time),
+                }
+            }
+            stats
+        })
+        .collect();
+    let mut stats = row_stats.iter().copied().fold(RenderStats::default(), RenderStats::merge);
+
+    if let Some(start) = mirror_start {
+        let height = resolution.height as usize;
+        for (offset, row) in mirrored.chunks_exact_mut(row_bytes).enumerate() {
+            let source = height - (start + offset);
+            row.copy_from_slice(&computed[source * row_bytes..(source + 1) * row_bytes]);
+            stats = stats.merge(row_stats[source]);
+        }
+    }
+    Ok(stats)
+}
+
+/// Renders `rect` into an `L8` image as `write_data` does, also counting the pixels inside the set,
+/// which the gray levels alone do not tell apart from dark exterior.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::render::render_counting_inside;
+///
+/// let resolution = Resolution{width: 80, height: 60};
+/// let rect = Rect::from_bounds(-2.0, 0.5, -1.0, 1.0).unwrap();
+/// let params = EscapeParams::default();
+/// let (image, inside) = render_counting_inside(resolution, rect, 128, Precision::Single, params, 1.0).unwrap();
+/// let recount = make_calculations(resolution, rect, 128, Precision::Single, params).unwrap()
+///     .filter(|(_, escape_time)| escape_time.is_none())
+///     .count();
+/// assert_eq!(inside, recount as u64);
+/// assert!(inside > 0 && inside < 80 * 60);
+/// assert_eq!(image.data.len(), 80 * 60);
+/// ```
+pub fn render_counting_inside(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams, gamma: f32)
+                     -> Result<(Image, u64), RectError>{
+    let mut image = Image::new(&resolution);
+    let mut inside = 0;
+    let calculations = make_calculations(resolution, rect, limit, precision, params)?
+        .inspect(|(_, escape_time)| if escape_time.is_none() { inside += 1 });
+    write_data(&mut image, calculations, limit, gamma).expect("calculations match the image resolution");
+    Ok((image, inside))
+}
+
+/// Escape times of an image computed coarse to fine: the first pass computes every `step`th pixel of
+/// every `step`th row and fills the `step` by `step` block below and right of it, and each further pass
+/// halves the step and computes only the pixels no earlier pass did. After the pass at step 1 the grid
+/// holds exactly what `make_calculations` computes.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::grid::EscapeGrid;
+/// use mandelbrot::render::ProgressiveRender;
+///
+/// let resolution = Resolution{width: 45, height: 30};
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.0, 1.0).unwrap();
+/// let params = EscapeParams::default();
+/// let mut progressive = ProgressiveRender::new(resolution, rect, 100, Precision::Single, params, 8).unwrap();
+///
+/// assert_eq!(progressive.refine(), Some(8));
+/// // The preview is blocky, every pixel of a block showing its corner.
+/// assert_eq!(progressive.grid().get(7, 7), progressive.grid().get(0, 0));
+///
+/// let mut steps = vec![];
+/// while let Some(step) = progressive.refine() {
+///     steps.push(step);
+/// }
+/// assert_eq!(steps, [4, 2, 1]);
+///
+/// let direct = EscapeGrid::collect(&resolution, make_calculations(resolution, rect, 100, Precision::Single, params).unwrap()).unwrap();
+/// assert_eq!(progressive.into_grid().data, direct.data);
+/// ```
+pub struct ProgressiveRender{
+    resolution: Resolution,
+    rect:       Rect<f32>,
+    limit:      EscapeLimit,
+    precision:  Precision,
+    params:     EscapeParams,
+    grid:       EscapeGrid,
+    /// Step of the next pass, 0 once the pass at step 1 has run.
+    step:       u32,
+    first:      bool,
+}
+
+impl ProgressiveRender{
+    /// Starts at `coarsest` rounded up to a power of two, so that every pass halves the step exactly.
+    /// Fails if `rect` does not pass `Rect::validate`.
+    pub fn new(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, precision: Precision, params: EscapeParams, coarsest: u32)
+            -> Result<Self, RectError>{
+        rect.validate()?;
+        let grid = EscapeGrid::new(&resolution);
+        Ok(ProgressiveRender{resolution, rect, limit, precision, params, grid, step: coarsest.max(1).next_power_of_two(), first: true})
+    }
+
+    /// Runs the next pass, returning its step, or `None` once the grid is complete.
+    pub fn refine(&mut self) -> Option<u32>{
+        let step = self.step;
+        if step == 0 {
+            return None;
+        }
+        let Resolution{width, height} = self.resolution;
+        let first = self.first;
+        // Pixels on both even multiples of the step were computed by the pass before.
+        let points: Vec<(u32, u32)> = (0..height).step_by(step as usize)
+            .flat_map(|y| (0..width).step_by(step as usize).map(move |x| (x, y)))
+            .filter(|(x, y)| first || x % (2 * step) != 0 || y % (2 * step) != 0)
+            .collect();
+
+        let (limit, precision, params) = (self.limit, self.precision, self.params);
+        let mapper = coordinate_mapper(self.resolution, self.rect, precision, params.orientation);
+        let escape_times: Vec<_> = points.par_iter()
+            .map(|&(x, y)| escape_time_at(mapper(x as f64, y as f64), limit, precision, params))
+            .collect();
+
+        for (&(x, y), escape_time) in points.iter().zip(escape_times) {
+            for row in y..(y + step).min(height) {
+                let start = row as usize * width as usize + x as usize;
+                let end = row as usize * width as usize + (x + step).min(width) as usize;
+                self.grid.data[start..end].fill(escape_time);
+            }
+        }
+
+        self.step /= 2;
+        self.first = false;
+        Some(step)
+    }
+
+    /// The escape times so far, exact at the pixels computed and copied from their block's corner
+    /// elsewhere.
+    pub fn grid(&self) -> &EscapeGrid{
+        &self.grid
+    }
+
+    pub fn into_grid(self) -> EscapeGrid{
+        self.grid
+    }
+}
+
+/// Everything `render` needs for one image, checked once by `RenderConfigBuilder::build` so the
+/// render itself cannot fail.
+#[derive(Clone, Debug)]
+pub struct RenderConfig{
+    resolution: Resolution,
+    rect:       Rect<f32>,
+    limit:      EscapeLimit,
+    precision:  Precision,
+    params:     EscapeParams,
+    palette:    Option<Gradient>,
+    gamma:      f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError{
+    /// The builder was never given this setting, which has no sensible default.
+    Missing(&'static str),
+    ZeroDimension(&'static str),
+    ZeroLimit,
+    Rect(RectError),
+    /// Gamma must be a finite number above zero.
+    Gamma(f32),
+}
+
+impl fmt::Display for ConfigError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            ConfigError::Missing(name) => write!(f, "the render needs a {name}"),
+            ConfigError::ZeroDimension(name) => write!(f, "{name} must be greater than zero"),
+            ConfigError::ZeroLimit => write!(f, "the iteration limit must be greater than zero"),
+            ConfigError::Rect(err) => write!(f, "{err}"),
+            ConfigError::Gamma(gamma) => write!(f, "gamma must be a positive number, not {gamma}"),
+        }
+    }
+}
+
+impl Error for ConfigError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            ConfigError::Rect(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<RectError> for ConfigError{
+    fn from(err: RectError) -> Self{
+        ConfigError::Rect(err)
+    }
+}
+
+/// Collects the settings of a `RenderConfig`. The resolution and rect are required; the rest
+/// default to 256 iterations at the precision the rect needs, `EscapeParams::default()`, grayscale
+/// and a gamma of 1.
+///
+/// ```
+/// use mandelbrot::geometry::{Range, Rect, RectError, Resolution};
+/// use mandelbrot::render::{ConfigError, RenderConfig};
+///
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.5, 1.5).unwrap();
+/// let resolution = Resolution{width: 64, height: 48};
+/// assert!(RenderConfig::builder().resolution(resolution).rect(rect).limit(100).build().is_ok());
+///
+/// assert_eq!(RenderConfig::builder().rect(rect).build().unwrap_err(), ConfigError::Missing("resolution"));
+/// assert_eq!(RenderConfig::builder().resolution(resolution).build().unwrap_err(), ConfigError::Missing("rect"));
+/// let flat = Resolution{width: 64, height: 0};
+/// assert_eq!(RenderConfig::builder().resolution(flat).rect(rect).build().unwrap_err(), ConfigError::ZeroDimension("height"));
+/// assert_eq!(RenderConfig::builder().resolution(resolution).rect(rect).limit(0).build().unwrap_err(), ConfigError::ZeroLimit);
+/// let backwards = Rect{x: Range{min: 1.0, max: -2.0}, y: rect.y};
+/// assert_eq!(
+///     RenderConfig::builder().resolution(resolution).rect(backwards).build().unwrap_err(),
+///     ConfigError::Rect(RectError::Empty{axis: "x", min: 1.0, max: -2.0}),
+/// );
+/// assert!(matches!(RenderConfig::builder().resolution(resolution).rect(rect).gamma(f32::NAN).build(), Err(ConfigError::Gamma(_))));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RenderConfigBuilder{
+    resolution: Option<Resolution>,
+    rect:       Option<Rect<f32>>,
+    limit:      Option<EscapeLimit>,
+    precision:  Option<Precision>,
+    params:     Option<EscapeParams>,
+    palette:    Option<Gradient>,
+    gamma:      Option<f32>,
+}
+
+impl RenderConfigBuilder{
+    pub fn resolution(mut self, resolution: Resolution) -> Self{
+        self.resolution = Some(resolution);
+        self
+    }
+
+    pub fn rect(mut self, rect: Rect<f32>) -> Self{
+        self.rect = Some(rect);
+        self
+    }
+
+    pub fn limit(mut self, limit: EscapeLimit) -> Self{
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Overrides the precision `Precision::for_rect` would pick.
+    pub fn precision(mut self, precision: Precision) -> Self{
+        self.precision = Some(precision);
+        self
+    }
+
+    pub fn params(mut self, params: EscapeParams) -> Self{
+        self.params = Some(params);
+        self
+    }
+
+    /// Colors the image with `palette` instead of rendering it in grayscale.
+    pub fn palette(mut self, palette: Gradient) -> Self{
+        self.palette = Some(palette);
+        self
+    }
+
+    /// Gamma of the gray levels, not applied to palettes.
+    pub fn gamma(mut self, gamma: f32) -> Self{
+        self.gamma = Some(gamma);
+        self
+    }
+
+    pub fn build(self) -> Result<RenderConfig, ConfigError>{
+        let resolution = self.resolution.ok_or(ConfigError::Missing("resolution"))?;
+        let rect = self.rect.ok_or(ConfigError::Missing("rect"))?;
+        if resolution.width == 0 {
+            return Err(ConfigError::ZeroDimension("width"));
+        }
+        if resolution.height == 0 {
+            return Err(ConfigError::ZeroDimension("height"));
+        }
+        let limit = self.limit.unwrap_or(256);
+        if limit == 0 {
+            return Err(ConfigError::ZeroLimit);
+        }
+        rect.validate()?;
+        let gamma = self.gamma.unwrap_or(1.0);
+        if !(gamma.is_finite() && gamma > 0.0) {
+            return Err(ConfigError::Gamma(gamma));
+        }
+        Ok(RenderConfig{
+            resolution, rect, limit, gamma,
+            precision: self.precision.unwrap_or_else(|| Precision::for_rect(&rect, &resolution)),
+            params:    self.params.unwrap_or_default(),
+            palette:   self.palette,
+        })
+    }
+}
+
+impl RenderConfig{
+    pub fn builder() -> RenderConfigBuilder{
+        RenderConfigBuilder::default()
+    }
+
+    pub fn resolution(&self) -> Resolution{
+        self.resolution
+    }
+
+    pub fn rect(&self) -> Rect<f32>{
+        self.rect
+    }
+
+    pub fn limit(&self) -> EscapeLimit{
+        self.limit
+    }
+
+    pub fn precision(&self) -> Precision{
+        self.precision
+    }
+
+    pub fn params(&self) -> EscapeParams{
+        self.params
+    }
+
+    pub fn palette(&self) -> Option<&Gradient>{
+        self.palette.as_ref()
+    }
+
+    pub fn gamma(&self) -> f32{
+        self.gamma
+    }
+
+    /// `make_calculations` over the configured view.
+    pub fn calculations(&self) -> impl Iterator<Item = ((u32, u32), Option<EscapeLimit>)> {
+        make_calculations(self.resolution, self.rect, self.limit, self.precision, self.params).expect("validated by the builder")
+    }
+}
+
+/// Renders the configured view in grayscale (`L8`), or into `Rgb8` with the palette. See
+/// `render_with_stats` for the escape times it came across.
+///
+/// ```
+/// use mandelbrot::color::Gradient;
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::image::PixelFormat;
+/// use mandelbrot::render::{render, RenderConfig};
+///
+/// let config = RenderConfig::builder()
+///     .resolution(Resolution{width: 32, height: 24})
+///     .rect(Rect::from_bounds(-2.0, 1.0, -1.5, 1.5).unwrap())
+///     .palette(Gradient::ultra_fractal())
+///     .build()
+///     .unwrap();
+/// let image = render(&config);
+/// assert_eq!(image.format, PixelFormat::Rgb8);
+/// assert_eq!(image.data.len(), 32 * 24 * 3);
+/// ```
+pub fn render(config: &RenderConfig) -> Image{
+    render_with_stats(config).0
+}
+
+/// `render`, also returning the stats of the escape times, which the colored pixels no longer tell
+/// apart from one another.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Rect, Resolution};
+/// use mandelbrot::grid::EscapeGrid;
+/// use mandelbrot::render::{render_with_stats, RenderConfig};
+///
+/// let resolution = Resolution{width: 90, height: 61};
+/// // Centered on the real axis, so the lower half is mirrored rather than computed.
+/// let rect = Rect::from_bounds(-2.0, 1.0, -1.0, 1.0).unwrap();
+/// let config = RenderConfig::builder().resolution(resolution).rect(rect).limit(200).build().unwrap();
+/// let (_, stats) = render_with_stats(&config);
+///
+/// let calculations = make_calculations(resolution, rect, 200, Precision::for_rect(&rect, &resolution), EscapeParams::default());
+/// let grid = EscapeGrid::collect(&resolution, calculations.unwrap()).unwrap();
+/// assert_eq!(stats.total, grid.data.len() as u64);
+/// assert_eq!(stats.inside_count, grid.data.iter().filter(|value| value.is_none()).count() as u64);
+/// assert_eq!(stats.min_escape, grid.data.iter().flatten().min().copied());
+/// assert_eq!(stats.max_escape, grid.data.iter().flatten().max().copied());
+/// ```
+pub fn render_with_stats(config: &RenderConfig) -> (Image, RenderStats){
+    match &config.palette {
+        Some(gradient) => {
+            let mut image = Image::with_format(&config.resolution, PixelFormat::Rgb8);
+            let lut = PaletteLut::new(config.limit, gradient);
+            let mut stats = RenderStats::default();
+            let calculations = config.calculations().inspect(|&(_, escape_time)| stats.record(escape_time));
+            write_colored_lut_data(&mut image, calculations, &lut).expect("calculations match the image resolution");
+            (image, stats)
+        }
+        None => {
+            let mut image = Image::new(&config.resolution);
+            let stats = render_parallel(&mut image, config.rect, config.limit, config.precision, config.params, config.gamma)
+                .expect("validated by RenderConfig::build");
+            (image, stats)
+        }
+    }
+}
+
+/// Renders `rect` in grayscale, or colored with `palette`, at the precision the rect needs.
+pub fn render_image(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, palette: Option<&Gradient>) -> Result<Image, ConfigError>{
+    let mut builder = RenderConfig::builder().resolution(resolution).rect(rect).limit(limit);
+    if let Some(gradient) = palette {
+        builder = builder.palette(gradient.clone());
+    }
+    Ok(render(&builder.build()?))
+}
+
+#[derive(Debug)]
+pub enum RenderError{
+    Config(ConfigError),
+    Encoding(SaveError),
+}
+
+impl fmt::Display for RenderError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self {
+            RenderError::Config(err) => write!(f, "{err}"),
+            RenderError::Encoding(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for RenderError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self {
+            RenderError::Config(err) => Some(err),
+            RenderError::Encoding(err) => Some(err),
+        }
+    }
+}
+
+impl From<ConfigError> for RenderError{
+    fn from(err: ConfigError) -> Self{
+        RenderError::Config(err)
+    }
+}
+
+impl From<SaveError> for RenderError{
+    fn from(err: SaveError) -> Self{
+        RenderError::Encoding(err)
+    }
+}
+
+/// Renders `rect` with `render_image` and returns it as PNG bytes, touching neither the filesystem
+/// nor any global state, so it can run on any thread, such as a server's blocking task pool.
+///
+/// ```
+/// use mandelbrot::color::Gradient;
+/// use mandelbrot::geometry::{Range, Rect, Resolution};
+/// use mandelbrot::render::render_tile;
+///
+/// let rect = Rect{x: Range{min: -2.0, max: 1.0}, y: Range{min: -1.5, max: 1.5}};
+/// let png = render_tile(Resolution{width: 32, height: 24}, rect, 64, Some(&Gradient::ultra_fractal())).unwrap();
+/// let decoded = image::load_from_memory(&png).unwrap();
+/// assert_eq!((decoded.width(), decoded.height()), (32, 24));
+/// ```
+pub fn render_tile(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit, palette: Option<&Gradient>)
+                   -> Result<Vec<u8>, RenderError>{
+    let image = render_image(resolution, rect, limit, palette)?;
+    Ok(encode_image(&image, OutputFormat::Png)?)
+}
@@ -0,0 +1,122 @@
+use num::Complex;
+use wide::f32x8;
+
+use crate::compute::{in_cardioid_or_bulb, narrow, pixel_coordinates, Convergence, EscapeLimit, Orientation, Precision};
+use crate::geometry::{Rect, RectError, Resolution};
+
+const LANES: usize = 8;
+
+/// Pixels handed to `calculate_escape_times_simd` at once by `make_calculations_simd`.
+const BATCH: usize = LANES * 32;
+
+/// Escape times of up to eight points iterated side by side. Lanes drop out of the iteration as they
+/// escape or converge, and the loop ends once every lane has.
+fn escape_lanes(cs: &[Complex<f32>], limit: EscapeLimit) -> [Option<EscapeLimit>; LANES]{
+    let mut results = [None; LANES];
+    let mut c_re = [0.0; LANES];
+    let mut c_im = [0.0; LANES];
+    let mut active = 0_u32;
+    for (lane, c) in cs.iter().enumerate() {
+        c_re[lane] = c.re;
+        c_im[lane] = c.im;
+        // As in `calculate_escape_time`, non-finite points escape at once instead of passing for inside.
+        if !c.re.is_finite() || !c.im.is_finite() {
+            results[lane] = Some(0);
+        } else if !in_cardioid_or_bulb(*c) {
+            active |= 1 << lane;
+        }
+    }
+
+    let (c_re, c_im) = (f32x8::new(c_re), f32x8::new(c_im));
+    let bailout_sqr = f32x8::splat(4.0);
+    let convergence_epsilon = Convergence::Auto.epsilon(limit).map(f32x8::splat);
+
+    let mut re = f32x8::ZERO;
+    let mut im = f32x8::ZERO;
+    for i in 0..limit {
+        if active == 0 {
+            break;
+        }
+
+        let norm_sqr = re * re + im * im;
+
+        let mut escaped = norm_sqr.simd_gt(bailout_sqr).to_bitmask() & active;
+        active &= !escaped;
+        while escaped != 0 {
+            results[escaped.trailing_zeros() as usize] = Some(i);
+            escaped &= escaped - 1;
+        }
+
+        if let Some(epsilon) = convergence_epsilon.filter(|_| i > 0) {
+            active &= !norm_sqr.simd_le(epsilon).to_bitmask();
+        }
+
+        let next_re = (re * re - im * im) + c_re;
+        let next_im = (re * im + im * re) + c_im;
+        re = next_re;
+        im = next_im;
+    }
+
+    results
+}
+
+/// Vectorized `calculate_escape_time` with `EscapeParams::default()`, giving exactly the same results.
+///
+/// ```
+/// use mandelbrot::compute::{calculate_escape_time, EscapeParams};
+/// use mandelbrot::simd::calculate_escape_times_simd;
+/// use num::Complex;
+///
+/// // Boundary points, the cardioid and the period-2 bulb, and interior points outside both that the
+/// // convergence shortcut ends, and non-finite points: 17 points, so the last chunk leaves seven lanes padded.
+/// let cs: Vec<Complex<f32>> = [
+///     (-0.75, 0.1), (0.25, 0.0), (-1.25, 0.0), (-0.1, 0.65), (0.3, 0.5),
+///     (0.0, 0.0), (-0.2, 0.2), (-1.0, 0.0), (-1.05, 0.1),
+///     (-0.12, 0.75), (-1.31, 0.0), (-0.12, -0.75), (2.0, 2.0),
+///     (f32::NAN, 0.0), (0.0, f32::INFINITY), (f32::NEG_INFINITY, f32::NAN), (-0.5, 0.0),
+/// ].into_iter().map(|(re, im)| Complex{re, im}).collect();
+/// for limit in [64, 1000] {
+///     let scalar: Vec<_> = cs.iter().map(|&c| calculate_escape_time(c, limit, EscapeParams::default())).collect();
+///     assert_eq!(calculate_escape_times_simd(&cs, limit), scalar);
+/// }
+/// ```
+pub fn calculate_escape_times_simd(cs: &[Complex<f32>], limit: EscapeLimit) -> Vec<Option<EscapeLimit>>{
+    let mut escape_times = Vec::with_capacity(cs.len());
+    for chunk in cs.chunks(LANES) {
+        escape_times.extend_from_slice(&escape_lanes(chunk, limit)[..chunk.len()]);
+    }
+    escape_times
+}
+
+/// `make_calculations` in single precision with the default parameters, evaluated in batches through
+/// `calculate_escape_times_simd`. Fails if `rect` does not pass `Rect::validate`.
+///
+/// ```
+/// use mandelbrot::compute::{make_calculations, EscapeParams, Precision};
+/// use mandelbrot::geometry::{Range, Rect, RectError, Resolution};
+/// use mandelbrot::simd::make_calculations_simd;
+///
+/// // 37 x 23 pixels fill neither the batches nor the last chunk of eight.
+/// let resolution = Resolution{width: 37, height: 23};
+/// let rect = Rect::from_bounds(-2.0, 0.6, -1.2, 1.2).unwrap();
+/// let scalar: Vec<_> = make_calculations(resolution, rect, 300, Precision::Single, EscapeParams::default()).unwrap().collect();
+/// assert_eq!(make_calculations_simd(resolution, rect, 300).unwrap().collect::<Vec<_>>(), scalar);
+///
+/// let nan = Rect{x: Range{min: f32::NAN, max: 1.0}, y: Range{min: -1.0, max: 1.0}};
+/// assert!(matches!(make_calculations_simd(resolution, nan, 300), Err(RectError::NonFinite{axis: "x"})));
+/// ```
+pub fn make_calculations_simd(resolution: Resolution, rect: Rect<f32>, limit: EscapeLimit)
+                     -> Result<impl Iterator<Item = ((u32, u32), Option<EscapeLimit>)>, RectError> {
+    rect.validate()?;
+    let mut coordinates = pixel_coordinates(resolution, rect, Precision::Single, Orientation::default());
+
+    Ok(std::iter::from_fn(move || {
+        let batch: Vec<_> = coordinates.by_ref().take(BATCH).collect();
+        (!batch.is_empty()).then_some(batch)
+    })
+    .flat_map(move |batch| {
+        let cs: Vec<Complex<f32>> = batch.iter().map(|(_, c)| narrow(*c)).collect();
+        let escape_times = calculate_escape_times_simd(&cs, limit);
+        batch.into_iter().map(|(position, _)| position).zip(escape_times)
+    }))
+}